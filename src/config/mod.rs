@@ -0,0 +1,105 @@
+use serde::Deserialize;
+
+use crate::library::{path_pattern, tag};
+use crate::scrobble::{lastfm, listenbrainz};
+use crate::ui::widgets::album_art::ArtMode;
+
+#[derive(Default, Deserialize)]
+pub struct SplayConfig {
+    #[serde(default)]
+    pub media: Media,
+    #[serde(default)]
+    pub library: LibraryConfig,
+    #[serde(default)]
+    pub ui: UIConfig,
+    #[serde(default)]
+    pub searches: Vec<SavedSearch>,
+    #[serde(default)]
+    pub smart_playlists: Vec<SmartPlaylist>,
+    #[serde(default)]
+    pub scrobbling: ScrobbleConfig,
+}
+
+#[derive(Default, Deserialize)]
+pub struct Media {
+    pub directories: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct LibraryConfig {
+    // patterns used to guess metadata from the path of files with missing tags
+    pub path_patterns: Vec<String>,
+    // split artist and genre tags into multiple values on these
+    pub tag_separators: Vec<String>,
+    // write ratings to the files as POPM and FMPS_Rating tags
+    pub write_ratings: bool,
+}
+
+impl Default for LibraryConfig {
+    fn default() -> Self {
+        LibraryConfig {
+            path_patterns: path_pattern::DEFAULT_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            tag_separators: tag::DEFAULT_SEPARATORS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            write_ratings: false,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct UIConfig {
+    // how cover art is drawn: auto, halfblocks, sixel, kitty or off
+    pub album_art: ArtMode,
+}
+
+// a named query shown in the browser, e.g. `query = "genre:jazz year:..1970"`
+#[derive(Clone, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+}
+
+// a playlist kept up to date from rules, e.g. `rules = "plays:0 sort:-added limit:100"`
+#[derive(Clone, Deserialize)]
+pub struct SmartPlaylist {
+    pub name: String,
+    pub rules: String,
+}
+
+// services to scrobble to, those left out are off
+#[derive(Default, Deserialize)]
+pub struct ScrobbleConfig {
+    pub listenbrainz: Option<ListenBrainzConfig>,
+    pub lastfm: Option<LastFmConfig>,
+}
+
+#[derive(Deserialize)]
+pub struct ListenBrainzConfig {
+    pub token: String,
+    #[serde(default = "listenbrainz_url")]
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+pub struct LastFmConfig {
+    pub api_key: String,
+    pub secret: String,
+    pub session_key: String,
+    #[serde(default = "lastfm_url")]
+    pub url: String,
+}
+
+fn listenbrainz_url() -> String {
+    listenbrainz::DEFAULT_URL.to_string()
+}
+
+fn lastfm_url() -> String {
+    lastfm::DEFAULT_URL.to_string()
+}
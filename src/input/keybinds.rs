@@ -46,13 +46,6 @@ impl Default for Keybinds {
             },
             AppRequests::UIRequests(UIRequests::Up),
         );
-        // lookup.insert(
-        //     KeyEvent {
-        //         code: KeyCode::Enter,
-        //         modifiers: KeyModifiers::NONE,
-        //     },
-        //     AppRequests::UIRequests(UIRequests::Enter),
-        // );
         lookup.insert(
             KeyEvent {
                 code: KeyCode::Down,
@@ -72,7 +65,21 @@ impl Default for Keybinds {
                 code: KeyCode::Enter,
                 modifiers: KeyModifiers::NONE,
             },
-            AppRequests::PlayerRequests(PlayerRequests::Start),
+            AppRequests::UIRequests(UIRequests::Enter),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Tab,
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::NextTab),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('r'),
+                modifiers: KeyModifiers::CONTROL,
+            },
+            AppRequests::Rescan,
         );
         lookup.insert(
             KeyEvent {
@@ -112,6 +119,176 @@ impl Default for Keybinds {
             },
             AppRequests::PlayerRequests(PlayerRequests::Stop),
         );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('i'),
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::ShowImportReport),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('e'),
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::EditTags),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('E'),
+                modifiers: KeyModifiers::SHIFT,
+            },
+            AppRequests::UIRequests(UIRequests::EditAlbumTags),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('a'),
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::AddToPlaylist),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('n'),
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::NewPlaylist),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('N'),
+                modifiers: KeyModifiers::SHIFT,
+            },
+            AppRequests::UIRequests(UIRequests::NewSmartPlaylist),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('S'),
+                modifiers: KeyModifiers::SHIFT,
+            },
+            AppRequests::UIRequests(UIRequests::EditSmartRules),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('R'),
+                modifiers: KeyModifiers::SHIFT,
+            },
+            AppRequests::UIRequests(UIRequests::RenamePlaylist),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('l'),
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::PlayPlaylist),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('s'),
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::ShufflePlaylist),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('z'),
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::CycleShuffle),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('P'),
+                modifiers: KeyModifiers::SHIFT,
+            },
+            AppRequests::UIRequests(UIRequests::PlayNext),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('A'),
+                modifiers: KeyModifiers::SHIFT,
+            },
+            AppRequests::UIRequests(UIRequests::AddToQueue),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::ClearQueue),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('r'),
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::CycleRepeat),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('o'),
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::ToggleRadio),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('t'),
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::CycleStatsPeriod),
+        );
+        for rating in 0..=5 {
+            lookup.insert(
+                KeyEvent {
+                    code: KeyCode::Char((b'0' + rating) as char),
+                    modifiers: KeyModifiers::NONE,
+                },
+                AppRequests::UIRequests(UIRequests::SetRating(rating)),
+            );
+        }
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('L'),
+                modifiers: KeyModifiers::SHIFT,
+            },
+            AppRequests::UIRequests(UIRequests::ToggleLoved),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('d'),
+                modifiers: KeyModifiers::NONE,
+            },
+            AppRequests::UIRequests(UIRequests::Delete),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('K'),
+                modifiers: KeyModifiers::SHIFT,
+            },
+            AppRequests::UIRequests(UIRequests::MoveUp),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('J'),
+                modifiers: KeyModifiers::SHIFT,
+            },
+            AppRequests::UIRequests(UIRequests::MoveDown),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('I'),
+                modifiers: KeyModifiers::SHIFT,
+            },
+            AppRequests::UIRequests(UIRequests::ImportPlaylist),
+        );
+        lookup.insert(
+            KeyEvent {
+                code: KeyCode::Char('X'),
+                modifiers: KeyModifiers::SHIFT,
+            },
+            AppRequests::UIRequests(UIRequests::ExportPlaylist),
+        );
 
         return Keybinds { lookup };
     }
@@ -21,6 +21,19 @@ pub fn listen(app_state: Arc<Mutex<AppState>>, main_tx: Sender<AppRequests>) {
             .unwrap_or_else(|| Duration::from_secs(0));
         if crossterm::event::poll(timeout).unwrap() {
             if let Event::Key(key) = event::read().unwrap() {
+                // Typing into the tag editor or a playlist name
+                if app_state.lock().unwrap().ui.text_input {
+                    let request = match key.code {
+                        KeyCode::Char(ch) => Some(UIRequests::EditInput(ch)),
+                        KeyCode::Backspace => Some(UIRequests::Backspace),
+                        _ => None,
+                    };
+                    if let Some(request) = request {
+                        _ = main_tx.send(AppRequests::UIRequests(request));
+                        continue 'input;
+                    }
+                }
+
                 //Handle search input
                 //TODO: move to another function "handle_search_input" and have it return a bool
                 //(should continue searching or not)
@@ -0,0 +1,116 @@
+use image::ImageFormat;
+use lofty::{PictureType, Tag};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Names (without extension) of image files in an album directory that are used as its cover
+const FOLDER_ART_NAMES: [&str; 5] = ["cover", "folder", "front", "album", "albumart"];
+const FOLDER_ART_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+// thumbnails are scaled down to fit in this many pixels on each side
+const THUMBNAIL_SIZE: u32 = 300;
+
+// Thumbnails of cover art stored as `<cache dir>/splay/artwork/<md5 of the original image>.png`.
+// Identical covers embedded in every track of an album are only decoded and stored once.
+#[derive(Clone)]
+pub struct ArtworkCache {
+    dir: PathBuf,
+    // album directory -> key of the cover found in it, so each directory is only searched once
+    folders: HashMap<PathBuf, Option<String>>,
+}
+
+impl Default for ArtworkCache {
+    fn default() -> Self {
+        ArtworkCache {
+            dir: cache_dir(),
+            folders: HashMap::new(),
+        }
+    }
+}
+
+impl ArtworkCache {
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.png", key))
+    }
+
+    // Embedded front cover (or any embedded picture) first, then an image next to the file
+    pub fn find(&mut self, tag: Option<&Tag>, song_path: &str) -> Option<String> {
+        if let Some(tag) = tag {
+            let pictures = tag.pictures();
+            let picture = pictures
+                .iter()
+                .find(|p| p.pic_type() == PictureType::CoverFront)
+                .or_else(|| pictures.first());
+            if let Some(key) = picture.and_then(|p| self.store(p.data())) {
+                return Some(key);
+            }
+        }
+
+        let dir = Path::new(song_path).parent()?.to_path_buf();
+        if let Some(key) = self.folders.get(&dir) {
+            return key.to_owned();
+        }
+        let key = folder_art(&dir)
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|data| self.store(&data));
+        self.folders.insert(dir, key.to_owned());
+        key
+    }
+
+    // Returns the key of the thumbnail, creating it if it isn't cached yet
+    fn store(&self, data: &[u8]) -> Option<String> {
+        let key = format!("{:x}", md5::compute(data));
+        let path = self.path(&key);
+        if path.exists() {
+            return Some(key);
+        }
+        let image = match image::load_from_memory(data) {
+            Ok(image) => image,
+            Err(err) => {
+                warn!("Could not decode artwork: {}", err);
+                return None;
+            }
+        };
+        if let Err(err) = fs::create_dir_all(&self.dir) {
+            warn!("Could not create {}: {}", self.dir.display(), err);
+            return None;
+        }
+        let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+        match thumbnail.save_with_format(&path, ImageFormat::Png) {
+            Ok(_) => Some(key),
+            Err(err) => {
+                warn!("Could not save artwork to {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+}
+
+// $XDG_CACHE_HOME/splay/artwork, or the platform equivalent
+pub fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("splay")
+        .join("artwork")
+}
+
+fn folder_art(dir: &Path) -> Option<PathBuf> {
+    let entries = fs::read_dir(dir).ok()?;
+    // prefer names earlier in FOLDER_ART_NAMES, e.g. cover.jpg over folder.jpg
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter_map(|path| {
+            let extension = path.extension()?.to_str()?;
+            if !FOLDER_ART_EXTENSIONS
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(extension))
+            {
+                return None;
+            }
+            let stem = path.file_stem()?.to_string_lossy().to_lowercase();
+            let rank = FOLDER_ART_NAMES.iter().position(|name| *name == stem)?;
+            Some((rank, path))
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, path)| path)
+}
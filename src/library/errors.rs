@@ -1,43 +1,126 @@
-use std::error::Error;
-use std::fmt;
+use lofty::LoftyError;
+use std::io;
+use thiserror::Error;
 
-//Include more details about cause of ImportError
+#[derive(Debug, Error)]
 pub enum ImportError {
-    MissingData,
-    FileNotFound,
-    Parsing,
+    #[error("{path}: file not found")]
+    FileNotFound { path: String },
+    #[error("{path}: path is not valid unicode")]
+    InvalidPath { path: String },
+    #[error("{path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{path}: could not parse file ({source})")]
+    Parsing {
+        path: String,
+        #[source]
+        source: LoftyError,
+    },
+    #[error("{path}: unsupported audio format")]
+    UnsupportedFormat { path: String },
+    #[error("{path}: no title tag and no path pattern matched")]
+    MissingTitle { path: String },
 }
 
-impl Error for ImportError {}
-
-impl fmt::Display for ImportError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl ImportError {
+    pub fn path(&self) -> &str {
         match self {
-            Self::MissingData => {
-                return write!(f, "Could not gather parts of metadata.");
-            }
-            Self::FileNotFound => {
-                return write!(f, "file not found");
-            }
-            Self::Parsing => {
-                return write!(f, "Parsing error");
-            }
+            Self::FileNotFound { path }
+            | Self::InvalidPath { path }
+            | Self::Io { path, .. }
+            | Self::Parsing { path, .. }
+            | Self::UnsupportedFormat { path }
+            | Self::MissingTitle { path } => path,
         }
     }
-}
 
-impl fmt::Debug for ImportError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    // short description of the cause without the path
+    pub fn reason(&self) -> String {
         match self {
-            Self::MissingData => {
-                return write!(f, "Could not gather parts of metadata.");
-            }
-            Self::FileNotFound => {
-                return write!(f, "file not found");
-            }
-            Self::Parsing => {
-                return write!(f, "Parsing error");
-            }
+            Self::FileNotFound { .. } => "file not found".to_string(),
+            Self::InvalidPath { .. } => "path is not valid unicode".to_string(),
+            Self::Io { source, .. } => format!("IO error: {}", source),
+            Self::Parsing { source, .. } => format!("parse error: {}", source),
+            Self::UnsupportedFormat { .. } => "unsupported audio format".to_string(),
+            Self::MissingTitle { .. } => "missing title".to_string(),
+        }
+    }
+
+    pub fn from_lofty(path: &str, err: LoftyError) -> Self {
+        match err {
+            LoftyError::BadExtension(_) | LoftyError::UnknownFormat => Self::UnsupportedFormat {
+                path: path.to_string(),
+            },
+            LoftyError::Io(source) => Self::Io {
+                path: path.to_string(),
+                source,
+            },
+            source => Self::Parsing {
+                path: path.to_string(),
+                source,
+            },
         }
     }
 }
+
+#[derive(Debug, Error)]
+pub enum TagWriteError {
+    #[error("{path}: could not write tags ({source})")]
+    Write {
+        path: String,
+        #[source]
+        source: LoftyError,
+    },
+    #[error("{path}: not in the library")]
+    NotInLibrary { path: String },
+    #[error("{path}: {field} can't be stored in this file's tags")]
+    Unsupported { path: String, field: &'static str },
+    #[error("tags were written but the file could not be read back: {0}")]
+    Reimport(#[from] ImportError),
+}
+
+#[derive(Debug, Error)]
+pub enum QueryError {
+    #[error("unknown field \"{0}\"")]
+    UnknownField(String),
+    #[error("{field}: \"{value}\" is not a number or range")]
+    InvalidNumber { field: String, value: String },
+    #[error("{field}: \"{value}\" is not yes or no")]
+    InvalidFlag { field: String, value: String },
+    #[error("missing closing quote")]
+    UnclosedQuote,
+    #[error("can't sort by \"{0}\"")]
+    UnknownSort(String),
+}
+
+#[derive(Debug, Error)]
+pub enum PlaylistError {
+    #[error("a playlist named \"{0}\" already exists")]
+    Exists(String),
+    #[error("playlist names can't be empty")]
+    EmptyName,
+    #[error("{path}: not an M3U, PLS or XSPF playlist")]
+    UnsupportedFormat { path: String },
+    #[error("{path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("could not save playlists to {path}: {source}")]
+    Save {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("invalid rules: {0}")]
+    Rules(#[from] QueryError),
+    #[error("\"{0}\" is a smart playlist, its tracks follow its rules")]
+    Smart(String),
+    #[error("\"{0}\" is defined in config.toml")]
+    FromConfig(String),
+}
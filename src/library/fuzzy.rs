@@ -0,0 +1,238 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use super::song::Song;
+
+// points added per field a query word matched in, titles rank above artists and albums
+const TITLE_WEIGHT: i64 = 20;
+const ARTIST_WEIGHT: i64 = 10;
+const ALBUM_WEIGHT: i64 = 10;
+const GENRE_WEIGHT: i64 = 0;
+const PLAY_COUNT_WEIGHT: f64 = 15.0;
+
+pub struct SearchHit<'a> {
+    pub song: &'a Song,
+    pub score: i64,
+    // char indices of the matched characters in the title and album title
+    pub title_matches: Vec<usize>,
+    pub album_matches: Vec<usize>,
+}
+
+struct Match {
+    score: i64,
+    positions: Vec<usize>,
+}
+
+// Lowercased with diacritics removed, so "Björk" becomes "bjork"
+pub fn fold(text: &str) -> String {
+    fold_indexed(text).into_iter().map(|(ch, _)| ch).collect()
+}
+
+// folded chars of `text`, each with the index of the char of `text` it came from
+fn fold_indexed(text: &str) -> Vec<(char, usize)> {
+    let mut folded = Vec::new();
+    for (idx, ch) in text.chars().enumerate() {
+        for decomposed in ch.nfd().filter(|c| !is_combining_mark(*c)) {
+            folded.extend(decomposed.to_lowercase().map(|c| (c, idx)));
+        }
+    }
+    folded
+}
+
+// folded words of a search query
+pub fn query_words(query: &str) -> Vec<Vec<char>> {
+    fold(query)
+        .split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.chars().collect())
+        .collect()
+}
+
+// Scores `song` against every word of the query, None unless all of them match in one of the
+// title, artists, album or genres. Frequently played songs get a boost.
+pub fn score_song<'a>(song: &'a Song, query: &[Vec<char>]) -> Option<SearchHit<'a>> {
+    let title = fold_indexed(&song.title);
+    let album = fold_indexed(&song.album_title);
+    let artists: Vec<_> = song.artists.iter().map(|a| fold_indexed(a)).collect();
+    let genres: Vec<_> = song.genres.iter().map(|g| fold_indexed(g)).collect();
+
+    let mut hit = SearchHit {
+        song,
+        score: 0,
+        title_matches: Vec::new(),
+        album_matches: Vec::new(),
+    };
+    for word in query {
+        let title_match = match_word(word, &title).map(|m| (m, TITLE_WEIGHT));
+        let album_match = match_word(word, &album).map(|m| (m, ALBUM_WEIGHT));
+        let other_match = artists
+            .iter()
+            .filter_map(|artist| match_word(word, artist).map(|m| (m, ARTIST_WEIGHT)))
+            .chain(
+                genres
+                    .iter()
+                    .filter_map(|genre| match_word(word, genre).map(|m| (m, GENRE_WEIGHT))),
+            )
+            .max_by_key(|(m, weight)| m.score + weight);
+
+        let best = [&title_match, &album_match, &other_match]
+            .into_iter()
+            .flatten()
+            .map(|(m, weight)| m.score + weight)
+            .max()?;
+        hit.score += best;
+        // highlight every shown field the word matched in, not only the best one
+        if let Some((m, _)) = title_match {
+            hit.title_matches.extend(m.positions);
+        }
+        if let Some((m, _)) = album_match {
+            hit.album_matches.extend(m.positions);
+        }
+    }
+    hit.score += ((song.play_count as f64 + 1.0).ln() * PLAY_COUNT_WEIGHT) as i64;
+    hit.title_matches.sort_unstable();
+    hit.title_matches.dedup();
+    hit.album_matches.sort_unstable();
+    hit.album_matches.dedup();
+    Some(hit)
+}
+
+// Best match of `word` in `text`: a substring beats a subsequence starting at a word, which
+// beats a word within a few typos of `word`
+fn match_word(word: &[char], text: &[(char, usize)]) -> Option<Match> {
+    let chars: Vec<char> = text.iter().map(|(ch, _)| *ch).collect();
+    let to_positions = |range: Vec<usize>| -> Vec<usize> {
+        let mut positions: Vec<usize> = range.into_iter().map(|idx| text[idx].1).collect();
+        positions.dedup();
+        positions
+    };
+    let len = word.len() as i64;
+
+    let substring = (0..chars.len())
+        .filter(|start| chars[*start..].starts_with(word))
+        .max_by_key(|start| (is_word_start(&chars, *start), std::cmp::Reverse(*start)));
+    if let Some(start) = substring {
+        let mut score = 100 + 10 * len;
+        if is_word_start(&chars, start) {
+            score += 30;
+        }
+        if start == 0 {
+            score += 10;
+        }
+        return Some(Match {
+            score,
+            positions: to_positions((start..start + word.len()).collect()),
+        });
+    }
+
+    let subsequence = (0..chars.len())
+        .filter(|start| is_word_start(&chars, *start) && chars[*start] == word[0])
+        .filter_map(|start| subsequence(word, &chars, start))
+        .max_by_key(|(score, _)| *score);
+    if let Some((score, positions)) = subsequence {
+        return Some(Match {
+            score,
+            positions: to_positions(positions),
+        });
+    }
+
+    let max_typos = match word.len() {
+        0..=3 => return None,
+        4..=7 => 1,
+        _ => 2,
+    };
+    let mut best: Option<Match> = None;
+    for (start, end) in word_bounds(&chars) {
+        let text_word = &chars[start..end];
+        // compare against the start of longer words so typos in a prefix still match
+        let prefix = &text_word[..text_word.len().min(word.len())];
+        let typos = edit_distance(word, text_word).min(edit_distance(word, prefix));
+        if typos > max_typos {
+            continue;
+        }
+        let score = 8 * len - 25 * typos as i64;
+        if best.as_ref().is_none_or(|best| score > best.score) {
+            best = Some(Match {
+                score,
+                positions: to_positions((start..start + prefix.len()).collect()),
+            });
+        }
+    }
+    best
+}
+
+// greedy subsequence match of `word` in `chars` from `start`, rejected if it's spread out
+fn subsequence(word: &[char], chars: &[char], start: usize) -> Option<(i64, Vec<usize>)> {
+    let mut positions = Vec::with_capacity(word.len());
+    let mut pos = start;
+    for ch in word {
+        while pos < chars.len() && chars[pos] != *ch {
+            pos += 1;
+        }
+        if pos == chars.len() {
+            return None;
+        }
+        positions.push(pos);
+        pos += 1;
+    }
+    let span = positions[positions.len() - 1] - start + 1;
+    if span > word.len() * 3 {
+        return None;
+    }
+    let mut score = 10 * word.len() as i64 - (span - word.len()) as i64;
+    for pair in positions.windows(2) {
+        // consecutive chars, or a jump to the start of the next word
+        if pair[1] == pair[0] + 1 || is_word_start(chars, pair[1]) {
+            score += 5;
+        }
+    }
+    Some((score, positions))
+}
+
+fn is_word_start(chars: &[char], idx: usize) -> bool {
+    idx == 0 || !chars[idx - 1].is_alphanumeric()
+}
+
+// (start, end) of every word in `chars`
+fn word_bounds(chars: &[char]) -> Vec<(usize, usize)> {
+    let mut bounds = Vec::new();
+    let mut start = None;
+    for (idx, ch) in chars.iter().enumerate() {
+        match (ch.is_alphanumeric(), start) {
+            (true, None) => start = Some(idx),
+            (false, Some(s)) => {
+                bounds.push((s, idx));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        bounds.push((s, chars.len()));
+    }
+    bounds
+}
+
+// Damerau-Levenshtein distance (optimal string alignment), swapped letters count as one typo
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut dist = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                dist = dist.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = dist;
+        }
+    }
+    rows[a.len()][b.len()]
+}
@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::playlist;
+use super::song::Song;
+use super::unix_time;
+
+// a song counts as played after half of it or this much was listened to
+const PLAYED_AFTER: Duration = Duration::from_secs(4 * 60);
+const DAY: u64 = 24 * 3600;
+
+// A song that was listened to long enough to count as played. The tags are kept so the history
+// stays readable after the song is removed from the library.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Play {
+    pub path: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_artist: String,
+    pub duration_ms: u64,
+    // when the song started, in seconds since the epoch
+    pub time: u64,
+    pub listened_ms: u64,
+    // moved on from before the end
+    pub skipped: bool,
+}
+
+impl Play {
+    // None if not enough of the song was listened to
+    pub fn new(song: &Song, listened: Duration, skipped: bool) -> Option<Play> {
+        let duration = Duration::from_millis(song.duration_ms);
+        // without a duration only the time listened counts
+        let half_played = duration > Duration::ZERO && listened * 2 > duration;
+        if listened < PLAYED_AFTER && !half_played {
+            return None;
+        }
+        Some(Play {
+            path: song.path.to_owned(),
+            title: song.title.to_owned(),
+            artist: song.track_artist.to_owned(),
+            album: song.album_title.to_owned(),
+            album_artist: song.album_artist.to_owned(),
+            duration_ms: song.duration_ms,
+            time: unix_time().saturating_sub(listened.as_secs()),
+            listened_ms: listened.as_millis() as u64,
+            skipped,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Period {
+    Week,
+    #[default]
+    Month,
+    Year,
+    All,
+}
+
+impl Period {
+    pub fn next(&self) -> Period {
+        match self {
+            Period::Week => Period::Month,
+            Period::Month => Period::Year,
+            Period::Year => Period::All,
+            Period::All => Period::Week,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Period::Week => "last 7 days",
+            Period::Month => "last 30 days",
+            Period::Year => "last year",
+            Period::All => "all time",
+        }
+    }
+
+    fn start(&self) -> u64 {
+        let days = match self {
+            Period::Week => 7,
+            Period::Month => 30,
+            Period::Year => 365,
+            Period::All => return 0,
+        };
+        unix_time().saturating_sub(days * DAY)
+    }
+}
+
+// Every play, oldest first, appended to `history` in the data directory as they happen
+pub struct History {
+    plays: Vec<Play>,
+    path: PathBuf,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            plays: Vec::new(),
+            path: history_path(),
+        }
+    }
+}
+
+impl History {
+    pub fn load() -> History {
+        History::load_from(history_path())
+    }
+
+    // the plays saved to `path`, which new plays are appended to
+    pub fn load_from(path: PathBuf) -> History {
+        let bytes = fs::read(&path).unwrap_or_default();
+        let mut plays = Vec::new();
+        let mut rest = bytes.as_slice();
+        // end of the last play read in full
+        let mut end = 0;
+        while let Ok(play) = bincode::deserialize_from(&mut rest) {
+            plays.push(play);
+            end = bytes.len() - rest.len();
+        }
+        // a play that was cut off while being written ends the history, drop it so the plays
+        // appended after it can be read again
+        if end < bytes.len() {
+            warn!(
+                "Dropping {} unreadable bytes at the end of the history",
+                bytes.len() - end
+            );
+            let truncated = OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .and_then(|file| file.set_len(end as u64));
+            if let Err(err) = truncated {
+                error!("Could not truncate {}: {}", path.display(), err);
+            }
+        }
+        History { plays, path }
+    }
+
+    pub fn add(&mut self, play: Play) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let result = bincode::serialize_into(file, &play);
+        self.plays.push(play);
+        Ok(result?)
+    }
+
+    // number of plays and time listened since the start of `period`
+    pub fn total(&self, period: Period) -> (usize, Duration) {
+        let plays = self.since(period);
+        let listened = plays.clone().map(|play| play.listened_ms).sum();
+        (plays.count(), Duration::from_millis(listened))
+    }
+
+    pub fn top_artists(&self, period: Period, count: usize) -> Vec<(String, usize)> {
+        self.top(period, count, |play| play.artist.to_owned())
+    }
+
+    pub fn top_albums(&self, period: Period, count: usize) -> Vec<(String, usize)> {
+        self.top(period, count, |play| {
+            format!("{} - {}", play.album, play.album_artist)
+        })
+    }
+
+    pub fn top_tracks(&self, period: Period, count: usize) -> Vec<(String, usize)> {
+        self.top(period, count, |play| {
+            format!("{} - {}", play.title, play.artist)
+        })
+    }
+
+    fn since(&self, period: Period) -> impl Iterator<Item = &Play> + Clone {
+        let start = period.start();
+        self.plays.iter().filter(move |play| play.time >= start)
+    }
+
+    // the most played names given by `key`, the ones played first break ties
+    fn top(
+        &self,
+        period: Period,
+        count: usize,
+        key: impl Fn(&Play) -> String,
+    ) -> Vec<(String, usize)> {
+        let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+        for (idx, play) in self.since(period).enumerate() {
+            counts.entry(key(play)).or_insert((0, idx)).0 += 1;
+        }
+        let mut top: Vec<(String, (usize, usize))> = counts.into_iter().collect();
+        top.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.1 .1.cmp(&b.1 .1)));
+        top.into_iter()
+            .take(count)
+            .map(|(name, (plays, _))| (name, plays))
+            .collect()
+    }
+}
+
+fn history_path() -> PathBuf {
+    playlist::data_dir().join("history")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn song(title: &str) -> Song {
+        let mut song = Song::new(title.to_string(), format!("/music/{}.mp3", title));
+        song.duration_ms = 180_000;
+        song
+    }
+
+    #[test]
+    fn short_listens_are_not_plays() {
+        let song = song("one");
+        assert!(Play::new(&song, Duration::from_secs(60), true).is_none());
+        assert!(Play::new(&song, Duration::from_secs(100), false).is_some());
+
+        let unknown = Song::new("unknown".to_string(), "/music/unknown.mp3".to_string());
+        assert!(Play::new(&unknown, Duration::from_secs(200), false).is_none());
+        assert!(Play::new(&unknown, PLAYED_AFTER, false).is_some());
+    }
+
+    #[test]
+    fn cut_off_play_is_dropped_from_the_file() {
+        let path = std::env::temp_dir().join(format!("splay-history-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut history = History::load_from(path.clone());
+        for title in ["one", "two"] {
+            let play = Play::new(&song(title), Duration::from_secs(100), false).unwrap();
+            history.add(play).unwrap();
+        }
+        // half of a play written when the app was killed
+        let partial = bincode::serialize(&history.plays[0]).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&partial[..partial.len() / 2]).unwrap();
+        drop(file);
+
+        let mut history = History::load_from(path.clone());
+        assert_eq!(history.plays.len(), 2);
+        let play = Play::new(&song("three"), Duration::from_secs(100), false).unwrap();
+        history.add(play).unwrap();
+
+        let history = History::load_from(path.clone());
+        let _ = fs::remove_file(&path);
+        let titles: Vec<&str> = history
+            .plays
+            .iter()
+            .map(|play| play.title.as_str())
+            .collect();
+        assert_eq!(titles, ["one", "two", "three"]);
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+
+// Minimal reader for ID3v2.4 text frames. ID3v2.4 stores multiple values in a single text
+// frame separated by null bytes, but lofty stops reading text at the first null so every
+// value after the first is lost. Only text frames listed in `ids` are decoded.
+pub fn read_v24_text_frames(path: &str, ids: &[&str]) -> HashMap<String, Vec<String>> {
+    match File::open(path) {
+        Ok(mut file) => read_v24_text_frames_from(&mut file, ids),
+        Err(_) => HashMap::new(),
+    }
+}
+
+// like `read_v24_text_frames`, with `reader` at the start of the file
+pub fn read_v24_text_frames_from(
+    reader: &mut impl Read,
+    ids: &[&str],
+) -> HashMap<String, Vec<String>> {
+    let mut frames = HashMap::new();
+
+    let mut header = [0u8; 10];
+    if reader.read_exact(&mut header).is_err() || &header[0..3] != b"ID3" || header[3] != 4 {
+        return frames;
+    }
+    let flags = header[5];
+    // tag-wide unsynchronisation is rare in v2.4 and not worth supporting here
+    if flags & 0x80 != 0 {
+        return frames;
+    }
+    // the size comes from the file, only allocate for what's actually there
+    let size = syncsafe(&header[6..10]) as usize;
+    let mut body = Vec::new();
+    match reader.take(size as u64).read_to_end(&mut body) {
+        Ok(read) if read == size => (),
+        _ => return frames,
+    }
+
+    let mut pos = 0;
+    if flags & 0x40 != 0 && body.len() >= 4 {
+        // skip the extended header, its size includes the size bytes themselves
+        pos = syncsafe(&body[0..4]) as usize;
+    }
+
+    while pos + 10 <= body.len() {
+        let id = &body[pos..pos + 4];
+        if id[0] == 0 {
+            break; // reached padding
+        }
+        let size = syncsafe(&body[pos + 4..pos + 8]) as usize;
+        let format_flags = body[pos + 9];
+        let start = pos + 10;
+        let end = start + size;
+        if end > body.len() {
+            break;
+        }
+        pos = end;
+
+        let id = String::from_utf8_lossy(id).to_string();
+        // skip compressed, encrypted or unsynchronised frames
+        if !ids.contains(&id.as_str()) || format_flags & 0x0E != 0 || size < 2 {
+            continue;
+        }
+        let mut content = &body[start..end];
+        if format_flags & 0x40 != 0 {
+            // group identifier
+            content = &content[1..];
+        }
+        if format_flags & 0x01 != 0 {
+            // data length indicator
+            if content.len() < 5 {
+                continue;
+            }
+            content = &content[4..];
+        }
+        let values = decode_values(content[0], &content[1..]);
+        if !values.is_empty() {
+            frames.insert(id, values);
+        }
+    }
+    frames
+}
+
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |size, byte| (size << 7) | (*byte as u32 & 0x7F))
+}
+
+fn decode_values(encoding: u8, data: &[u8]) -> Vec<String> {
+    let values: Vec<String> = match encoding {
+        // ISO-8859-1
+        0 => data
+            .split(|b| *b == 0)
+            .map(|value| value.iter().map(|b| *b as char).collect())
+            .collect(),
+        // UTF-16 with BOM, or UTF-16BE
+        1 | 2 => {
+            let units: Vec<[u8; 2]> = data.chunks_exact(2).map(|c| [c[0], c[1]]).collect();
+            units
+                .split(|unit| *unit == [0, 0])
+                .map(|value| decode_utf16(value, encoding == 2))
+                .collect()
+        }
+        // UTF-8
+        3 => data
+            .split(|b| *b == 0)
+            .map(|value| String::from_utf8_lossy(value).to_string())
+            .collect(),
+        _ => Vec::new(),
+    };
+    values
+        .into_iter()
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+fn decode_utf16(units: &[[u8; 2]], mut big_endian: bool) -> String {
+    let mut units = units;
+    match units.first() {
+        Some([0xFE, 0xFF]) => {
+            big_endian = true;
+            units = &units[1..];
+        }
+        Some([0xFF, 0xFE]) => {
+            big_endian = false;
+            units = &units[1..];
+        }
+        _ => (),
+    }
+    let units: Vec<u16> = units
+        .iter()
+        .map(|unit| match big_endian {
+            true => u16::from_be_bytes(*unit),
+            false => u16::from_le_bytes(*unit),
+        })
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+fn syncsafe_bytes(size: usize) -> [u8; 4] {
+    [
+        (size >> 21) as u8 & 0x7F,
+        (size >> 14) as u8 & 0x7F,
+        (size >> 7) as u8 & 0x7F,
+        size as u8 & 0x7F,
+    ]
+}
+
+// an ID3v2.4 tag with `frames`, the content of text frames starts with their encoding byte
+#[cfg(test)]
+pub fn v24_tag(frames: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (id, content) in frames {
+        body.extend_from_slice(id.as_bytes());
+        body.extend_from_slice(&syncsafe_bytes(content.len()));
+        body.extend_from_slice(&[0, 0]);
+        body.extend_from_slice(content);
+    }
+    let mut tag = b"ID3\x04\x00\x00".to_vec();
+    tag.extend_from_slice(&syncsafe_bytes(body.len()));
+    tag.extend(body);
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn reads_every_value_of_a_frame() {
+        let tag = v24_tag(&[
+            ("TPE1", b"\x03Artist A\0Artist B"),
+            ("TCON", b"\x01\xFF\xFER\0o\0c\0k\0\0\0J\0a\0z\0z\0"),
+            ("TIT2", b"\x03Title"),
+        ]);
+        let frames = read_v24_text_frames_from(&mut Cursor::new(tag), &["TPE1", "TCON"]);
+        assert_eq!(frames["TPE1"], ["Artist A", "Artist B"]);
+        assert_eq!(frames["TCON"], ["Rock", "Jazz"]);
+        assert!(!frames.contains_key("TIT2"));
+    }
+
+    #[test]
+    fn latin1_values_are_decoded() {
+        let tag = v24_tag(&[("TPE1", b"\x00Bj\xF6rk\0Sigur R\xF3s")]);
+        let frames = read_v24_text_frames_from(&mut Cursor::new(tag), &["TPE1"]);
+        assert_eq!(frames["TPE1"], ["Björk", "Sigur Rós"]);
+    }
+
+    #[test]
+    fn tag_larger_than_the_file_is_skipped() {
+        let mut tag = v24_tag(&[("TPE1", b"\x03Artist")]);
+        tag[6..10].copy_from_slice(&syncsafe_bytes(0x0FFF_FFFF));
+        let frames = read_v24_text_frames_from(&mut Cursor::new(tag), &["TPE1"]);
+        assert!(frames.is_empty());
+    }
+
+    #[test]
+    fn older_versions_are_left_to_lofty() {
+        let mut tag = v24_tag(&[("TPE1", b"\x03Artist A\0Artist B")]);
+        tag[3] = 3;
+        let frames = read_v24_text_frames_from(&mut Cursor::new(tag), &["TPE1"]);
+        assert!(frames.is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+use multimap::MultiMap;
+use std::collections::HashMap;
+
+use super::song::Song;
+
+// Lookup tables from metadata to positions in `Library::songs`.
+// Positions are only valid until a song is removed, after which the index must be rebuilt.
+#[derive(Clone, Default)]
+pub struct LibraryIndex {
+    // album artist -> album title -> tracks
+    albums: HashMap<String, MultiMap<String, usize>>,
+    artists: MultiMap<String, usize>,
+    genres: MultiMap<String, usize>,
+    years: MultiMap<String, usize>,
+    paths: HashMap<String, usize>,
+    // (album artist, album title) -> artwork key of the first track with artwork
+    artwork: HashMap<(String, String), String>,
+    // sort names from tags ("Beatles, The") used to order the keys above
+    album_artist_sort: HashMap<String, String>,
+    album_sort: HashMap<String, String>,
+    artist_sort: HashMap<String, String>,
+}
+
+impl LibraryIndex {
+    pub fn build(songs: &[Song]) -> Self {
+        let mut index = LibraryIndex::default();
+        for (idx, song) in songs.iter().enumerate() {
+            index.insert(idx, song);
+        }
+        index
+    }
+
+    pub fn insert(&mut self, idx: usize, song: &Song) {
+        self.paths.insert(song.path.to_owned(), idx);
+        self.albums
+            .entry(song.album_artist.to_owned())
+            .or_default()
+            .insert(song.album_title.to_owned(), idx);
+        for artist in song.artists.iter() {
+            self.artists.insert(artist.to_owned(), idx);
+        }
+        for genre in song.genres.iter() {
+            self.genres.insert(genre.to_owned(), idx);
+        }
+        if let Some(artwork) = &song.artwork {
+            self.artwork
+                .entry((song.album_artist.to_owned(), song.album_title.to_owned()))
+                .or_insert_with(|| artwork.to_owned());
+        }
+        if let Some(year) = &song.year {
+            self.years.insert(year.to_owned(), idx);
+        }
+        if let Some(sort) = &song.album_artist_sort {
+            self.album_artist_sort
+                .insert(song.album_artist.to_owned(), sort.to_owned());
+        }
+        if let Some(sort) = &song.album_sort {
+            self.album_sort
+                .insert(song.album_title.to_owned(), sort.to_owned());
+        }
+        // a sort name can only be attributed to an artist when the track credits a single one
+        if let (Some(sort), [artist]) = (&song.artist_sort, song.artists.as_slice()) {
+            self.artist_sort.insert(artist.to_owned(), sort.to_owned());
+        }
+    }
+
+    pub fn path(&self, path: &str) -> Option<usize> {
+        self.paths.get(path).copied()
+    }
+
+    pub fn album_artists(&self) -> Vec<&str> {
+        sorted(self.albums.keys(), &self.album_artist_sort)
+    }
+
+    pub fn albums(&self, album_artist: &str) -> Vec<&str> {
+        match self.albums.get(album_artist) {
+            Some(albums) => sorted(albums.keys(), &self.album_sort),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn album_tracks(&self, album_artist: &str, album: &str) -> &[usize] {
+        match self.albums.get(album_artist) {
+            Some(albums) => slice(albums, album),
+            None => &[],
+        }
+    }
+
+    pub fn album_artwork(&self, album_artist: &str, album: &str) -> Option<&str> {
+        self.artwork
+            .get(&(album_artist.to_string(), album.to_string()))
+            .map(|key| key.as_str())
+    }
+
+    pub fn artists(&self) -> Vec<&str> {
+        sorted(self.artists.keys(), &self.artist_sort)
+    }
+
+    pub fn artist_tracks(&self, artist: &str) -> &[usize] {
+        slice(&self.artists, artist)
+    }
+
+    pub fn genres(&self) -> Vec<&str> {
+        sorted(self.genres.keys(), &HashMap::new())
+    }
+
+    pub fn genre_tracks(&self, genre: &str) -> &[usize] {
+        slice(&self.genres, genre)
+    }
+
+    pub fn years(&self) -> Vec<&str> {
+        sorted(self.years.keys(), &HashMap::new())
+    }
+
+    pub fn year_tracks(&self, year: &str) -> &[usize] {
+        slice(&self.years, year)
+    }
+}
+
+fn sorted<'a>(
+    keys: impl Iterator<Item = &'a String>,
+    sort_names: &HashMap<String, String>,
+) -> Vec<&'a str> {
+    let mut keys: Vec<&str> = keys.map(|k| k.as_str()).collect();
+    keys.sort_by_cached_key(|k| match sort_names.get(*k) {
+        Some(sort) => sort.to_lowercase(),
+        None => k.to_lowercase(),
+    });
+    keys
+}
+
+fn slice<'a>(map: &'a MultiMap<String, usize>, key: &str) -> &'a [usize] {
+    match map.get_vec(key) {
+        Some(tracks) => tracks,
+        None => &[],
+    }
+}
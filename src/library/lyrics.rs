@@ -0,0 +1,150 @@
+use lofty::read_from_path;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use super::song::Song;
+use super::tag;
+
+pub struct LyricLine {
+    // when the line starts, None for unsynchronized lyrics
+    pub time: Option<Duration>,
+    pub text: String,
+}
+
+pub struct Lyrics {
+    pub lines: Vec<LyricLine>,
+    pub synced: bool,
+}
+
+impl Lyrics {
+    // Lyrics are looked up in order from a .lrc file next to the song, a SYLT frame, and the
+    // lyrics tag (which may itself contain LRC formatted text)
+    pub fn load(song: &Song) -> Option<Lyrics> {
+        let lrc_path = Path::new(&song.path).with_extension("lrc");
+        if let Ok(text) = fs::read_to_string(lrc_path) {
+            let lyrics = Lyrics::parse_lrc(&text);
+            if !lyrics.lines.is_empty() {
+                return Some(lyrics);
+            }
+        }
+        if let Some(lines) = tag::read_id3v2_synced_lyrics(&song.path, song.sample_rate) {
+            return Some(Lyrics {
+                lines: lines
+                    .into_iter()
+                    .map(|(time, text)| LyricLine {
+                        time: Some(time),
+                        text,
+                    })
+                    .collect(),
+                synced: true,
+            });
+        }
+        let file = read_from_path(&song.path, false).ok()?;
+        let text = tag::get_lyrics(file.primary_tag().or_else(|| file.first_tag())?)?;
+        let lyrics = Lyrics::parse_lrc(&text);
+        match lyrics.lines.is_empty() {
+            true => None,
+            false => Some(lyrics),
+        }
+    }
+
+    // Parses LRC ("[01:23.45]line"). Lines can have multiple timestamps, and text without any
+    // timestamps is treated as unsynchronized lyrics.
+    pub fn parse_lrc(text: &str) -> Lyrics {
+        let mut synced = Vec::new();
+        let mut unsynced = Vec::new();
+        // [offset:+/-ms], positive values make the lyrics appear sooner
+        let mut offset_ms: i64 = 0;
+
+        for line in text.lines() {
+            let mut rest = line.trim();
+            let mut times = Vec::new();
+            let mut metadata = false;
+            while let Some(tag_end) = rest.strip_prefix('[').and_then(|r| r.find(']')) {
+                let tag = &rest[1..tag_end + 1];
+                match parse_timestamp(tag) {
+                    Some(time) => times.push(time),
+                    None => {
+                        if let Some(offset) = tag.strip_prefix("offset:") {
+                            offset_ms = offset.trim().parse().unwrap_or(0);
+                        }
+                        metadata |= tag.contains(':');
+                    }
+                }
+                rest = rest[tag_end + 2..].trim_start();
+            }
+            // metadata tags like [ar:Artist], but not annotations like [Chorus]
+            if times.is_empty() && metadata {
+                continue;
+            }
+            if times.is_empty() {
+                unsynced.push(line.trim_end().to_string());
+            }
+            for time in times {
+                synced.push((time, rest.to_string()));
+            }
+        }
+
+        if synced.is_empty() {
+            // drop leading and trailing blank lines
+            while unsynced.last().is_some_and(|l| l.is_empty()) {
+                unsynced.pop();
+            }
+            let start = unsynced.iter().position(|l| !l.is_empty()).unwrap_or(0);
+            return Lyrics {
+                lines: unsynced
+                    .into_iter()
+                    .skip(start)
+                    .map(|text| LyricLine { time: None, text })
+                    .collect(),
+                synced: false,
+            };
+        }
+        synced.sort_by_key(|(time, _)| *time);
+        Lyrics {
+            lines: synced
+                .into_iter()
+                .map(|(time, text)| LyricLine {
+                    time: Some(apply_offset(time, offset_ms)),
+                    text,
+                })
+                .collect(),
+            synced: true,
+        }
+    }
+
+    // index of the line being sung at `progress`
+    pub fn current_line(&self, progress: Duration) -> Option<usize> {
+        if !self.synced {
+            return None;
+        }
+        self.lines
+            .iter()
+            .rposition(|line| line.time.is_some_and(|time| time <= progress))
+    }
+}
+
+// mm:ss, mm:ss.xx or mm:ss.xxx
+fn parse_timestamp(tag: &str) -> Option<Duration> {
+    let (mins, secs) = tag.split_once(':')?;
+    let mins: u64 = mins.trim().parse().ok()?;
+    let (secs, frac) = match secs.split_once(['.', ':']) {
+        Some((secs, frac)) => (secs, frac),
+        None => (secs, ""),
+    };
+    let secs: u64 = secs.trim().parse().ok()?;
+    let frac_ms = match frac.len() {
+        0 => 0,
+        1..=3 => frac.parse::<u64>().ok()? * 10u64.pow(3 - frac.len() as u32),
+        _ => frac[..3].parse().ok()?,
+    };
+    Some(Duration::from_millis((mins * 60 + secs) * 1000 + frac_ms))
+}
+
+fn apply_offset(time: Duration, offset_ms: i64) -> Duration {
+    match offset_ms >= 0 {
+        true => time.saturating_sub(Duration::from_millis(offset_ms as u64)),
+        false => time + Duration::from_millis(offset_ms.unsigned_abs()),
+    }
+}
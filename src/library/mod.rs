@@ -1,114 +1,552 @@
+pub mod artwork;
 pub mod errors;
+pub mod fuzzy;
+pub mod history;
+pub mod id3;
+pub mod index;
+pub mod lyrics;
+pub mod path_pattern;
+pub mod playlist;
+pub mod playlist_file;
+pub mod properties;
+pub mod query;
+pub mod radio;
+pub mod report;
 pub mod search;
+pub mod smart_playlist;
 pub mod song;
 pub mod tag;
+pub mod tag_edit;
+pub mod user_data;
 
 use crate::library::song::Song;
+use artwork::ArtworkCache;
 use bincode;
-use errors::ImportError;
-use lofty::read_from_path;
+use errors::{ImportError, QueryError, TagWriteError};
+use fuzzy::SearchHit;
+use index::LibraryIndex;
+use path_pattern::PathPattern;
+use query::Query;
+use report::{ImportReport, ImportStatus, SkipReason};
+use search::SearchDB;
+use std::collections::{BTreeMap, HashSet};
 use std::error::Error;
 use std::fs;
-use std::path::Path;
-use std::time::Instant;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tag_edit::TagField;
+use user_data::UserData;
 
+// Start of the db file. The version goes up whenever `Song` changes, a db with another version
+// is dropped and its songs imported again, with the data in `UserData` restored from `<db>.user`.
+const DB_MAGIC: &[u8; 8] = b"splaydb\0";
+const DB_VERSION: u32 = 1;
+
+#[derive(Clone)]
 pub struct Library {
     pub songs: Vec<Song>,
+    // used to fill in metadata for files with missing tags
+    pub path_patterns: Vec<PathPattern>,
+    // split artist and genre tags into multiple values, e.g. "Rock; Indie"
+    pub tag_separators: Vec<String>,
+    // also write ratings to the files
+    pub write_ratings: bool,
+    index: LibraryIndex,
+    search: SearchDB,
+    artwork: ArtworkCache,
+    // incremented every time songs are added, removed or modified
+    revision: u64,
+    // user data of songs that aren't in the library, restored if they're imported (again)
+    user_data: BTreeMap<String, UserData>,
 }
 
 impl Default for Library {
     fn default() -> Self {
-        Library { songs: vec![] }
+        Library {
+            songs: vec![],
+            path_patterns: path_pattern::default_patterns(),
+            tag_separators: tag::DEFAULT_SEPARATORS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            write_ratings: false,
+            index: LibraryIndex::default(),
+            search: SearchDB::new(),
+            artwork: ArtworkCache::default(),
+            revision: 0,
+            user_data: BTreeMap::new(),
+        }
     }
 }
 
 impl Library {
     pub fn new() -> Library {
-        Library { songs: Vec::new() }
+        Library::default()
     }
 
-    // only supports wav, mp3, flac
-    pub fn import_file(&mut self, filepath: &str) -> Result<(), Box<dyn Error>> {
+    // songs that had some of their metadata guessed from their path
+    pub fn inferred_songs(&self) -> impl Iterator<Item = &Song> {
+        self.songs.iter().filter(|song| song.metadata_inferred)
+    }
+
+    // supports the formats lofty can read (mp3, flac, wav, ogg, opus, mp4, aiff, ape)
+    pub fn import_file(&mut self, filepath: &str) -> Result<ImportStatus, ImportError> {
         let path = if Path::new(filepath).exists() {
             match fs::canonicalize(filepath) {
                 Ok(path) => match path.to_str() {
                     Some(p) => p.to_string(),
-                    None => return Err(Box::new(ImportError::Parsing)), // only if path contains
-                                                                        // invalid unicode
+                    None => {
+                        return Err(ImportError::InvalidPath {
+                            path: filepath.to_string(),
+                        })
+                    }
                 },
-                Err(e) => return Err(Box::new(e)),
+                Err(source) => {
+                    return Err(ImportError::Io {
+                        path: filepath.to_string(),
+                        source,
+                    })
+                }
             }
         } else {
-            return Err(Box::new(ImportError::FileNotFound));
+            return Err(ImportError::FileNotFound {
+                path: filepath.to_string(),
+            });
         };
 
-        match read_from_path(filepath, false) {
-            Ok(file) => match file.primary_tag() {
-                Some(tag) => {
-                    let song = match Song::from_tag(tag, path) {
-                        Ok(song) => song,
-                        Err(err) => return Err(err),
-                    };
-                    self.songs.push(song);
-                }
-                _ => {
-                    return Err(Box::new(ImportError::Parsing));
-                }
-            },
-            Err(_) => {
-                return Err(Box::new(ImportError::Parsing));
+        if !is_audio_file(&path) {
+            return Ok(ImportStatus::Skipped(SkipReason::NotAudio));
+        }
+        if self.index.path(&path).is_some() {
+            return Ok(ImportStatus::Skipped(SkipReason::AlreadyImported));
+        }
+
+        let mut song = self.read_song(path)?;
+        match self.user_data.remove(&song.path) {
+            Some(data) => data.apply(&mut song),
+            None => song.added = unix_time(),
+        }
+        if song.metadata_inferred {
+            info!("Inferred missing metadata for {} from its path", song.path);
+        }
+        self.index.insert(self.songs.len(), &song);
+        self.search.insert_song(&song);
+        self.songs.push(song);
+        self.revision += 1;
+
+        Ok(ImportStatus::Imported)
+    }
+
+    // read the tags and properties of the file at the canonical `path`
+    fn read_song(&mut self, path: String) -> Result<Song, ImportError> {
+        let tag_file = match tag::read_file(&path) {
+            Ok(tag_file) => tag_file,
+            Err(err) => return Err(ImportError::from_lofty(&path, err)),
+        };
+        let file = &tag_file.file;
+        let tag = file.primary_tag().or_else(|| file.first_tag());
+        let mut song = match tag {
+            Some(tag) => Song::from_tag(
+                tag,
+                &tag_file,
+                path,
+                &self.path_patterns,
+                &self.tag_separators,
+            )?,
+            None => Song::from_path(path, &self.path_patterns)?,
+        };
+        song.artwork = self.artwork.find(tag, &song.path);
+        let mut properties = properties::from_lofty(file);
+        if properties.duration_ms == 0 {
+            // lofty reads properties from headers only, fall back to probing the stream
+            if let Some(probed) = properties::from_symphonia(&song.path) {
+                properties.duration_ms = probed.duration_ms;
+                properties.sample_rate = properties.sample_rate.or(probed.sample_rate);
+                properties.channels = properties.channels.or(probed.channels);
             }
         }
+        song.set_properties(properties);
+        song.modified = modified_time(&song.path);
+        if let Some(id3v2) = &tag_file.id3v2 {
+            let (recording_id, release_id) = tag::id3v2_musicbrainz_ids(id3v2);
+            song.musicbrainz_recording_id = song.musicbrainz_recording_id.or(recording_id);
+            song.musicbrainz_release_id = song.musicbrainz_release_id.or(release_id);
+        }
+        Ok(song)
+    }
 
+    // Write tag changes to the files at `paths` and reload them into the library
+    pub fn edit_tags(
+        &mut self,
+        paths: &[String],
+        changes: &[(TagField, String)],
+    ) -> Result<(), TagWriteError> {
+        let result = self._edit_tags(paths, changes);
+        self.rebuild_index();
+        result
+    }
+
+    // helper for edit_tags, stops at the first file that fails
+    fn _edit_tags(
+        &mut self,
+        paths: &[String],
+        changes: &[(TagField, String)],
+    ) -> Result<(), TagWriteError> {
+        for path in paths {
+            let idx = match self.songs.iter().position(|song| &song.path == path) {
+                Some(idx) => idx,
+                None => {
+                    return Err(TagWriteError::NotInLibrary {
+                        path: path.to_owned(),
+                    })
+                }
+            };
+            tag_edit::write(path, changes)?;
+            let mut song = self.read_song(path.to_owned())?;
+            song.keep_user_data(&self.songs[idx]);
+            self.search.remove_song(&self.songs[idx]);
+            self.search.insert_song(&song);
+            self.songs[idx] = song;
+        }
         Ok(())
     }
 
-    pub fn import_dir(&mut self, dir_path: &str) -> Result<(), Box<dyn Error>> {
+    pub fn import_dir(&mut self, dir_path: &str) -> Result<ImportReport, ImportError> {
         let now = Instant::now();
-        match self._import_dir(dir_path) {
-            Err(e) => return Err(e),
-            _ => (),
-        }
+        let mut report = ImportReport::default();
+        self._import_dir(dir_path, &mut report)?;
         let elapsed = now.elapsed();
+        info!("Took {:.3?} to import {}: {}", elapsed, dir_path, report);
+        for err in report.failed.iter() {
+            warn!("Failed to import {}", err);
+        }
         info!(
-            "Took {:.3?} to import {} files from {}",
-            elapsed,
-            self.songs.len(),
-            dir_path
+            "{} songs have metadata inferred from their path",
+            self.inferred_songs().count()
         );
-        Ok(())
+        let missing_artwork = self
+            .album_artists()
+            .iter()
+            .flat_map(|artist| self.albums(artist).into_iter().map(move |a| (*artist, a)))
+            .filter(|(artist, album)| self.album_artwork(artist, album).is_none())
+            .count();
+        info!("{} albums have no artwork", missing_artwork);
+        Ok(report)
     }
 
     // recursive helper function for import_dir
-    fn _import_dir(&mut self, dir_path: &str) -> Result<(), Box<dyn Error>> {
-        let entries = fs::read_dir(dir_path);
+    fn _import_dir(
+        &mut self,
+        dir_path: &str,
+        report: &mut ImportReport,
+    ) -> Result<(), ImportError> {
+        let entries = match fs::read_dir(dir_path) {
+            Ok(entries) => entries,
+            Err(source) => {
+                return Err(ImportError::Io {
+                    path: dir_path.to_string(),
+                    source,
+                })
+            }
+        };
         for entry in entries {
-            for tmp in entry {
-                match tmp {
-                    Ok(file) => {
-                        if file.path().is_dir() {
-                            match self._import_dir(file.path().to_str().unwrap()) {
-                                Err(e) => error!("{:?}", e),
-                                _ => (),
-                            }
-                        } else {
-                            let _ = self.import_file(file.path().to_str().unwrap());
-                        }
-                    }
-                    Err(e) => {
-                        return Err(Box::new(e));
-                    }
+            let entry_path = match entry {
+                Ok(entry) => entry.path(),
+                Err(source) => {
+                    report.failed.push(ImportError::Io {
+                        path: dir_path.to_string(),
+                        source,
+                    });
+                    continue;
+                }
+            };
+            let path = match entry_path.to_str() {
+                Some(path) => path.to_string(),
+                None => {
+                    report.failed.push(ImportError::InvalidPath {
+                        path: entry_path.to_string_lossy().to_string(),
+                    });
+                    continue;
                 }
+            };
+            if entry_path.is_dir() {
+                if let Err(err) = self._import_dir(&path, report) {
+                    report.failed.push(err);
+                }
+            } else {
+                let result = self.import_file(&path);
+                report.record(&path, result);
             }
         }
         Ok(())
     }
 
+    // Drop songs whose files no longer exist and import any new files in `dirs`
+    pub fn rescan(&mut self, dirs: &[String]) -> ImportReport {
+        let (songs, missing): (Vec<Song>, Vec<Song>) = std::mem::take(&mut self.songs)
+            .into_iter()
+            .partition(|song| Path::new(&song.path).exists());
+        self.songs = songs;
+        if !missing.is_empty() {
+            info!("Removed {} missing songs", missing.len());
+            for song in missing.iter() {
+                self.search.remove_song(song);
+                // in case they're only missing until a drive is mounted again
+                self.user_data
+                    .insert(song.path.to_owned(), UserData::of(song));
+            }
+            self.rebuild_index();
+        }
+        self.reload_modified();
+
+        let mut report = ImportReport::default();
+        for dir in dirs {
+            match self.import_dir(dir) {
+                Ok(dir_report) => report.merge(dir_report),
+                Err(err) => report.failed.push(err),
+            }
+        }
+        report
+    }
+
+    // Take over `scanned`, a copy of this library that was rescanned while this one kept being
+    // used. Plays, ratings and songs imported in the meantime are kept.
+    pub fn replace(&mut self, mut scanned: Library) {
+        for song in scanned.songs.iter_mut() {
+            if let Some(current) = self.song(&song.path) {
+                song.keep_user_data(current);
+            }
+        }
+        for song in self.songs.iter() {
+            if scanned.index.path(&song.path).is_none() && Path::new(&song.path).exists() {
+                scanned.index.insert(scanned.songs.len(), song);
+                scanned.search.insert_song(song);
+                scanned.songs.push(song.clone());
+            }
+        }
+        scanned.revision = self.revision.max(scanned.revision) + 1;
+        *self = scanned;
+    }
+
+    // read songs whose files changed since they were imported again
+    fn reload_modified(&mut self) {
+        let mut reloaded = 0;
+        for idx in 0..self.songs.len() {
+            if modified_time(&self.songs[idx].path) == self.songs[idx].modified {
+                continue;
+            }
+            match self.read_song(self.songs[idx].path.to_owned()) {
+                Ok(mut song) => {
+                    song.keep_user_data(&self.songs[idx]);
+                    self.search.remove_song(&self.songs[idx]);
+                    self.search.insert_song(&song);
+                    self.songs[idx] = song;
+                    reloaded += 1;
+                }
+                Err(err) => warn!("Could not reload {}", err),
+            }
+        }
+        if reloaded > 0 {
+            info!("Reloaded {} modified songs", reloaded);
+            self.rebuild_index();
+        }
+    }
+
+    fn rebuild_index(&mut self) {
+        self.index = LibraryIndex::build(&self.songs);
+        self.revision += 1;
+    }
+
+    // Songs matching the query `term` best first, see `Query` for the syntax
+    pub fn search(&self, term: &str) -> Result<Vec<SearchHit<'_>>, QueryError> {
+        Ok(self.query(&Query::parse(term)?))
+    }
+
+    // Songs passing the filters of `query`, ranked by how well they match its free text. The
+    // search index finds songs with words starting with the free text words, when it finds
+    // only a few every song is scored so typos still match.
+    pub fn query(&self, query: &Query) -> Vec<SearchHit<'_>> {
+        if query.words.is_empty() {
+            let mut hits: Vec<SearchHit> = self
+                .songs
+                .iter()
+                .filter(|song| query.matches(song))
+                .map(|song| SearchHit {
+                    song,
+                    score: 0,
+                    title_matches: Vec::new(),
+                    album_matches: Vec::new(),
+                })
+                .collect();
+            hits.sort_by(|a, b| a.song.title.cmp(&b.song.title));
+            return hits;
+        }
+        let text: Vec<String> = query.words.iter().map(|w| w.iter().collect()).collect();
+        let (titles, artists, albums) = self.search.find_matches(&text.join(" "));
+        let titles: HashSet<String> = titles.into_iter().collect();
+        let artists: HashSet<String> = artists.into_iter().collect();
+        let albums: HashSet<String> = albums.into_iter().collect();
+        let candidates: Vec<&Song> = self
+            .songs
+            .iter()
+            .filter(|song| {
+                titles.contains(&song.title)
+                    || albums.contains(&song.album_title)
+                    || song.artists.iter().any(|artist| artists.contains(artist))
+            })
+            .collect();
+
+        let songs: Vec<&Song> = match candidates.len() < MIN_INDEXED_RESULTS {
+            true => self.songs.iter().collect(),
+            false => candidates,
+        };
+        let mut hits: Vec<SearchHit> = songs
+            .into_iter()
+            .filter(|song| query.matches(song))
+            .filter_map(|song| fuzzy::score_song(song, &query.words))
+            .collect();
+        hits.sort_by(|a, b| b.score.cmp(&a.score).then(a.song.title.cmp(&b.song.title)));
+        hits
+    }
+
+    // counts a play of the song at `path`, `time` in seconds since the epoch
+    pub fn record_play(&mut self, path: &str, time: u64) {
+        let Some(idx) = self.index.path(path) else {
+            return;
+        };
+        if let Some(song) = self.songs.get_mut(idx) {
+            song.play_count += 1;
+            song.last_played = Some(time);
+            // smart playlists sorted or filtered by plays follow
+            self.revision += 1;
+        }
+    }
+
+    // Rates the song at `path` from 1 to 5 stars, 0 removes the rating. The rating is kept even
+    // if it can't be written to the file.
+    pub fn set_rating(&mut self, path: &str, rating: u8) -> Result<(), TagWriteError> {
+        let rating = rating.min(5);
+        self.song_mut(path)?.rating = rating;
+        self.revision += 1;
+        if self.write_ratings {
+            tag_edit::write_rating(path, rating)?;
+            // so the next rescan doesn't read the song again
+            self.song_mut(path)?.modified = modified_time(path);
+        }
+        Ok(())
+    }
+
+    pub fn set_loved(&mut self, path: &str, loved: bool) -> Result<(), TagWriteError> {
+        self.song_mut(path)?.loved = loved;
+        self.revision += 1;
+        Ok(())
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn album_artists(&self) -> Vec<&str> {
+        self.index.album_artists()
+    }
+
+    pub fn albums(&self, album_artist: &str) -> Vec<&str> {
+        self.index.albums(album_artist)
+    }
+
+    pub fn song(&self, path: &str) -> Option<&Song> {
+        self.songs.get(self.index.path(path)?)
+    }
+
+    // tracks of an album in the order they appear on the album
+    pub fn album_tracks(&self, album_artist: &str, album: &str) -> Vec<&Song> {
+        let mut songs = self.lookup(self.index.album_tracks(album_artist, album));
+        songs.sort_by_key(|song| song.track_position());
+        songs
+    }
+
+    // path of the cached cover art thumbnail for an album
+    pub fn album_artwork(&self, album_artist: &str, album: &str) -> Option<PathBuf> {
+        self.index
+            .album_artwork(album_artist, album)
+            .map(|key| self.artwork.path(key))
+    }
+
+    // the song's own artwork, or its album's if it has none
+    pub fn song_artwork(&self, song: &Song) -> Option<PathBuf> {
+        match &song.artwork {
+            Some(key) => Some(self.artwork.path(key)),
+            None => self.album_artwork(&song.album_artist, &song.album_title),
+        }
+    }
+
+    pub fn artists(&self) -> Vec<&str> {
+        self.index.artists()
+    }
+
+    pub fn artist_tracks(&self, artist: &str) -> Vec<&Song> {
+        self.by_album(self.lookup(self.index.artist_tracks(artist)))
+    }
+
+    pub fn genres(&self) -> Vec<&str> {
+        self.index.genres()
+    }
+
+    pub fn genre_tracks(&self, genre: &str) -> Vec<&Song> {
+        self.by_album(self.lookup(self.index.genre_tracks(genre)))
+    }
+
+    pub fn years(&self) -> Vec<&str> {
+        self.index.years()
+    }
+
+    pub fn year_tracks(&self, year: &str) -> Vec<&Song> {
+        self.by_album(self.lookup(self.index.year_tracks(year)))
+    }
+
+    fn lookup(&self, positions: &[usize]) -> Vec<&Song> {
+        positions
+            .iter()
+            .filter_map(|idx| self.songs.get(*idx))
+            .collect()
+    }
+
+    fn song_mut(&mut self, path: &str) -> Result<&mut Song, TagWriteError> {
+        let not_found = || TagWriteError::NotInLibrary {
+            path: path.to_owned(),
+        };
+        let idx = self.index.path(path).ok_or_else(not_found)?;
+        self.songs.get_mut(idx).ok_or_else(not_found)
+    }
+
+    fn by_album<'a>(&self, mut songs: Vec<&'a Song>) -> Vec<&'a Song> {
+        songs.sort_by(|a, b| {
+            (&a.album_artist, &a.album_title, a.track_position()).cmp(&(
+                &b.album_artist,
+                &b.album_title,
+                b.track_position(),
+            ))
+        });
+        songs
+    }
+
+    // the search index is saved next to the songs in `<path>.search`
     pub fn save_to_file(&self, path: String) -> Result<(), Box<dyn Error>> {
-        let db_file = match fs::File::create(path) {
+        self.search.save(&format!("{}.search", path), &self.songs)?;
+        let mut user_data = self.user_data.clone();
+        user_data.extend(
+            self.songs
+                .iter()
+                .map(|song| (song.path.to_owned(), UserData::of(song))),
+        );
+        user_data::save(&format!("{}.user", path), user_data)?;
+        let mut db_file = match fs::File::create(path) {
             Ok(f) => f,
             Err(err) => return Err(Box::new(err)), //TODO: replace this with library DB error type
         };
+        db_file.write_all(DB_MAGIC)?;
+        db_file.write_all(&DB_VERSION.to_le_bytes())?;
         for song in self.songs.iter() {
             match bincode::serialize_into(&db_file, song) {
                 Err(err) => return Err(Box::new(err)), //TODO: replace this with library DB error type
@@ -119,11 +557,24 @@ impl Library {
     }
 
     pub fn load_from_file(&mut self, path: String) -> Result<(), Box<dyn Error>> {
-        let db_file = match std::fs::File::open("db") {
+        let search_path = format!("{}.search", path);
+        let user_path = format!("{}.user", path);
+        if Path::new(&user_path).exists() {
+            match user_data::load(&user_path) {
+                Ok(user_data) => self.user_data = user_data,
+                Err(err) => error!("Could not load {}: {}", user_path, err),
+            }
+        }
+        let db_file = match std::fs::File::open(path) {
             Ok(f) => f,
             Err(e) => return Err(Box::new(e)),
         };
         let mut buf_reader = std::io::BufReader::new(db_file);
+        let mut header = [0u8; 12];
+        buf_reader.read_exact(&mut header)?;
+        if header[..8] != DB_MAGIC[..] || header[8..] != DB_VERSION.to_le_bytes() {
+            return Err("the library db has an older format, the songs are imported again".into());
+        }
         loop {
             let result: Result<Song, Box<bincode::ErrorKind>> =
                 bincode::deserialize_from(&mut buf_reader);
@@ -137,6 +588,144 @@ impl Library {
                 }
             }
         }
+        for song in self.songs.iter_mut() {
+            if let Some(user_data) = self.user_data.remove(&song.path) {
+                user_data.apply(song);
+            }
+        }
+        self.search = match SearchDB::load(&search_path, &self.songs) {
+            Ok(search) => search,
+            Err(err) => {
+                warn!("Could not load {}, rebuilding it: {}", search_path, err);
+                SearchDB::build(&self.songs)
+            }
+        };
+        self.rebuild_index();
         Ok(())
     }
 }
+
+// fewer prefix matches than this and the search falls back to scoring the whole library
+const MIN_INDEXED_RESULTS: usize = 20;
+
+// Files with these extensions are reported as failures rather than skipped when they can't be
+// imported, everything else (cover art, lyrics, playlists, ...) is skipped
+const AUDIO_EXTENSIONS: [&str; 17] = [
+    "mp3", "flac", "wav", "ogg", "oga", "opus", "m4a", "mp4", "aac", "aiff", "aif", "ape", "wma",
+    "wv", "alac", "mpc", "spx",
+];
+
+// modification time of a file in seconds since the epoch, 0 if it can't be read
+fn modified_time(path: &str) -> u64 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs())
+}
+
+// current time in seconds since the epoch
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+fn is_audio_file(path: &str) -> bool {
+    match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("splay-db-{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn remove_db(path: &str) {
+        for file in [
+            path.to_string(),
+            format!("{}.search", path),
+            format!("{}.user", path),
+        ] {
+            let _ = fs::remove_file(file);
+        }
+    }
+
+    fn rated_library() -> Library {
+        let mut library = Library::new();
+        let mut song = Song::new("Title".to_string(), "/music/title.mp3".to_string());
+        song.play_count = 2;
+        song.rating = 5;
+        library.songs.push(song);
+        library
+    }
+
+    #[test]
+    fn mp3_is_imported_with_raw_id3_frames() {
+        let mut bytes = id3::v24_tag(&[
+            ("TIT2", b"\x03Title"),
+            ("TPE1", b"\x03Artist A\0Artist B"),
+            ("UFID", b"http://musicbrainz.org\0recording-id"),
+            ("TXXX", b"\x03MusicBrainz Album Id\0release-id"),
+        ]);
+        // MPEG-1 layer III frames at 128 kbps and 44.1 kHz, 417 bytes each
+        for _ in 0..100 {
+            bytes.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x64]);
+            bytes.extend_from_slice(&[0; 413]);
+        }
+        let path = format!("{}.mp3", db_path("import"));
+        fs::write(&path, bytes).unwrap();
+
+        let mut library = Library::new();
+        let result = library.import_file(&path);
+        let _ = fs::remove_file(&path);
+        assert!(matches!(result, Ok(ImportStatus::Imported)));
+        let song = &library.songs[0];
+        assert_eq!(song.title, "Title");
+        assert_eq!(song.artists, ["Artist A", "Artist B"]);
+        assert_eq!(
+            song.musicbrainz_recording_id.as_deref(),
+            Some("recording-id")
+        );
+        assert_eq!(song.musicbrainz_release_id.as_deref(), Some("release-id"));
+        assert!(song.duration_ms > 0);
+    }
+
+    #[test]
+    fn songs_load_with_their_user_data() {
+        let path = db_path("load");
+        rated_library().save_to_file(path.clone()).unwrap();
+        let mut library = Library::new();
+        let result = library.load_from_file(path.clone());
+        remove_db(&path);
+        assert!(result.is_ok());
+        let song = library.song("/music/title.mp3").unwrap();
+        assert_eq!((song.play_count, song.rating), (2, 5));
+    }
+
+    #[test]
+    fn older_db_keeps_user_data() {
+        let path = db_path("older");
+        rated_library().save_to_file(path.clone()).unwrap();
+        // a db written before the current version
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[8..12].copy_from_slice(&(DB_VERSION - 1).to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+
+        let mut library = Library::new();
+        let result = library.load_from_file(path.clone());
+        remove_db(&path);
+        assert!(result.is_err());
+        assert!(library.songs.is_empty());
+        let user_data = &library.user_data["/music/title.mp3"];
+        assert_eq!((user_data.play_count, user_data.rating), (2, 5));
+    }
+}
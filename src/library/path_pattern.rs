@@ -0,0 +1,265 @@
+use std::path::{Component, Path};
+use std::str::FromStr;
+
+// Patterns tried in order when a file is missing tags. Each `/` separated segment of a pattern
+// is matched against the trailing directories of a file's path (the extension is ignored).
+pub const DEFAULT_PATTERNS: [&str; 5] = [
+    "{albumartist}/{album}/{track} - {artist} - {title}",
+    "{artist}/{album}/{track} - {title}",
+    "{artist}/{album}/{track}. {title}",
+    "{artist}/{album}/{track} {title}",
+    "{artist} - {title}",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Title,
+    Artist,
+    AlbumArtist,
+    Album,
+    Track,
+    Year,
+    // matches anything without recording it, written as `{}` or `{ignore}`
+    Ignore,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(String),
+    Field(Field),
+}
+
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    segments: Vec<Vec<Token>>,
+}
+
+// Metadata recovered from a file path by a PathPattern
+#[derive(Debug, Default, Clone)]
+pub struct PathMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub track_number: Option<String>,
+    pub year: Option<String>,
+}
+
+impl FromStr for PathPattern {
+    type Err = String;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        for segment in pattern.split('/').filter(|s| !s.is_empty()) {
+            segments.push(parse_segment(segment)?);
+        }
+        if segments.is_empty() {
+            return Err(format!("Path pattern \"{}\" is empty", pattern));
+        }
+        Ok(PathPattern { segments })
+    }
+}
+
+impl PathPattern {
+    // Match this pattern against the end of `path`, returning the captured metadata
+    pub fn matches(&self, path: &Path) -> Option<PathMetadata> {
+        let mut components: Vec<String> = path
+            .with_extension("")
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => part.to_str().map(|s| s.to_string()),
+                _ => None,
+            })
+            .collect();
+        if components.len() < self.segments.len() {
+            return None;
+        }
+        let components = components.split_off(components.len() - self.segments.len());
+
+        let mut metadata = PathMetadata::default();
+        for (tokens, component) in self.segments.iter().zip(components.iter()) {
+            let mut captures = Vec::new();
+            if !match_tokens(tokens, component, &mut captures) {
+                return None;
+            }
+            for (field, value) in captures {
+                let value = Some(value.trim().to_string());
+                match field {
+                    Field::Title => metadata.title = value,
+                    Field::Artist => metadata.artist = value,
+                    Field::AlbumArtist => metadata.album_artist = value,
+                    Field::Album => metadata.album = value,
+                    Field::Track => metadata.track_number = value,
+                    Field::Year => metadata.year = value,
+                    Field::Ignore => (),
+                }
+            }
+        }
+        Some(metadata)
+    }
+}
+
+// Try each pattern in order and return the metadata from the first one that matches
+pub fn infer(path: &Path, patterns: &[PathPattern]) -> PathMetadata {
+    patterns
+        .iter()
+        .find_map(|pattern| pattern.matches(path))
+        .unwrap_or_default()
+}
+
+pub fn default_patterns() -> Vec<PathPattern> {
+    DEFAULT_PATTERNS
+        .iter()
+        .filter_map(|p| p.parse().ok())
+        .collect()
+}
+
+fn parse_segment(segment: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = segment;
+    while !rest.is_empty() {
+        match rest.find('{') {
+            Some(0) => {
+                let end = match rest.find('}') {
+                    Some(end) => end,
+                    None => return Err(format!("Unclosed '{{' in path pattern \"{}\"", segment)),
+                };
+                let field = match rest[1..end].trim().to_lowercase().as_str() {
+                    "title" => Field::Title,
+                    "artist" => Field::Artist,
+                    "albumartist" | "album_artist" => Field::AlbumArtist,
+                    "album" => Field::Album,
+                    "track" => Field::Track,
+                    "year" => Field::Year,
+                    "" | "ignore" => Field::Ignore,
+                    other => return Err(format!("Unknown field {{{}}} in path pattern", other)),
+                };
+                if let Some(Token::Field(_)) = tokens.last() {
+                    return Err(format!(
+                        "Fields in path pattern \"{}\" must be separated by text",
+                        segment
+                    ));
+                }
+                tokens.push(Token::Field(field));
+                rest = &rest[end + 1..];
+            }
+            Some(start) => {
+                tokens.push(Token::Literal(rest[..start].to_string()));
+                rest = &rest[start..];
+            }
+            None => {
+                tokens.push(Token::Literal(rest.to_string()));
+                rest = "";
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// Fields match as little text as possible, so `{track} - {title}` splits on the first " - "
+fn match_tokens(tokens: &[Token], input: &str, captures: &mut Vec<(Field, String)>) -> bool {
+    match tokens.split_first() {
+        None => input.is_empty(),
+        Some((Token::Literal(literal), rest)) => match input.strip_prefix(literal.as_str()) {
+            Some(remaining) => match_tokens(rest, remaining, captures),
+            None => false,
+        },
+        Some((Token::Field(field), rest)) => {
+            for (idx, _) in input.char_indices().skip(1).chain([(input.len(), ' ')]) {
+                let value = &input[..idx];
+                if !is_valid(*field, value) {
+                    continue;
+                }
+                captures.push((*field, value.to_string()));
+                if match_tokens(rest, &input[idx..], captures) {
+                    return true;
+                }
+                captures.pop();
+            }
+            false
+        }
+    }
+}
+
+fn is_valid(field: Field, value: &str) -> bool {
+    let value = value.trim();
+    match field {
+        Field::Track => {
+            // allow "3", "03" and "1-03" (disc-track)
+            !value.is_empty() && value.chars().all(|c| c.is_ascii_digit() || c == '-')
+        }
+        Field::Year => value.len() == 4 && value.chars().all(|c| c.is_ascii_digit()),
+        _ => !value.is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer_default(path: &str) -> PathMetadata {
+        infer(Path::new(path), &default_patterns())
+    }
+
+    #[test]
+    fn default_patterns_parse() {
+        assert_eq!(default_patterns().len(), DEFAULT_PATTERNS.len());
+    }
+
+    #[test]
+    fn fields_are_read_from_the_last_directories() {
+        let metadata = infer_default("/music/Radiohead/OK Computer/02 - Paranoid Android.flac");
+        assert_eq!(metadata.artist.as_deref(), Some("Radiohead"));
+        assert_eq!(metadata.album.as_deref(), Some("OK Computer"));
+        assert_eq!(metadata.track_number.as_deref(), Some("02"));
+        assert_eq!(metadata.title.as_deref(), Some("Paranoid Android"));
+    }
+
+    #[test]
+    fn fields_match_as_little_as_possible() {
+        let metadata = infer_default("/music/Various/Mix/1-03 - Artist - Song - Live.mp3");
+        assert_eq!(metadata.album_artist.as_deref(), Some("Various"));
+        assert_eq!(metadata.track_number.as_deref(), Some("1-03"));
+        assert_eq!(metadata.artist.as_deref(), Some("Artist"));
+        assert_eq!(metadata.title.as_deref(), Some("Song - Live"));
+    }
+
+    #[test]
+    fn track_numbers_must_be_numbers() {
+        // "Intro" isn't a track number, so the last pattern is used
+        let metadata = infer_default("Artist - Intro.mp3");
+        assert_eq!(metadata.track_number, None);
+        assert_eq!(metadata.artist.as_deref(), Some("Artist"));
+        assert_eq!(metadata.title.as_deref(), Some("Intro"));
+    }
+
+    #[test]
+    fn years_and_ignored_fields() {
+        let pattern: PathPattern = "{artist}/{year} {}/{title}".parse().unwrap();
+        let metadata = pattern
+            .matches(Path::new("/music/Björk/1997 Homogenic/Jóga.ogg"))
+            .unwrap();
+        assert_eq!(metadata.year.as_deref(), Some("1997"));
+        assert_eq!(metadata.album, None);
+        assert_eq!(metadata.title.as_deref(), Some("Jóga"));
+        assert!(pattern
+            .matches(Path::new("/Björk/97 Homogenic/Jóga.ogg"))
+            .is_none());
+    }
+
+    #[test]
+    fn paths_shorter_than_the_pattern_dont_match() {
+        let pattern: PathPattern = "{artist}/{album}/{title}".parse().unwrap();
+        assert!(pattern.matches(Path::new("Album/Title.mp3")).is_none());
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        assert!("".parse::<PathPattern>().is_err());
+        assert!("//".parse::<PathPattern>().is_err());
+        assert!("{artist".parse::<PathPattern>().is_err());
+        assert!("{genre}/{title}".parse::<PathPattern>().is_err());
+        assert!("{track}{title}".parse::<PathPattern>().is_err());
+        assert!("{ALBUM_ARTIST}/{Title}".parse::<PathPattern>().is_ok());
+    }
+}
@@ -1,11 +1,446 @@
-use crate::library::song::Song;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
 
-struct Playlist {
-    songs: Vec<Song>,
+use super::errors::PlaylistError;
+use super::fuzzy;
+use super::playlist_file::{self, Entry, PlaylistFormat};
+use super::smart_playlist::SmartRules;
+use super::song::Song;
+use super::Library;
+
+// Songs are stored by path so a playlist survives rescans, paths that aren't in the library
+// (anymore) are kept but skipped when playing
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Playlist {
+    pub name: String,
+    #[serde(default)]
+    pub paths: Vec<String>,
+    // smart playlists have rules (see `SmartRules`), their paths are re-evaluated whenever the
+    // library changes instead of being edited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules: Option<String>,
+    // order of smart playlists sorted randomly, a new one is picked when the rules are set
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub seed: u32,
+    // smart playlists defined in config.toml aren't saved with the others
+    #[serde(skip)]
+    pub from_config: bool,
 }
 
 impl Playlist {
-    pub fn add_song() {}
+    pub fn new(name: String) -> Self {
+        Playlist {
+            name,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_smart(&self) -> bool {
+        self.rules.is_some()
+    }
+
+    pub fn add_song(&mut self, song: &Song) {
+        self.paths.push(song.path.to_owned());
+    }
+
+    // Reads an M3U/M3U8, PLS or XSPF file. Relative paths are resolved against the file's
+    // directory and entries whose path isn't in the library are matched by the end of their
+    // path (for libraries under another mount point) or by artist and title.
+    pub fn import(path: &Path, library: &Library) -> Result<Playlist, PlaylistError> {
+        let format = format(path)?;
+        let bytes = fs::read(path).map_err(|source| PlaylistError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let entries = format.parse(&playlist_file::decode(&bytes));
+        let mut unmatched = 0;
+        let paths = entries
+            .iter()
+            .map(|entry| {
+                let location = resolve(dir, &entry.location);
+                match find_song(&location, entry, library) {
+                    Some(song) => song.path.to_owned(),
+                    None => {
+                        unmatched += 1;
+                        location
+                    }
+                }
+            })
+            .collect();
+        if unmatched > 0 {
+            warn!(
+                "{}: {} entries are not in the library",
+                path.display(),
+                unmatched
+            );
+        }
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Playlist {
+            name,
+            paths,
+            ..Default::default()
+        })
+    }
+
+    // Writes the playlist in the format of the file extension, with paths relative to the
+    // file when the songs are in the same directory tree
+    pub fn export(&self, path: &Path, library: &Library) -> Result<(), PlaylistError> {
+        let format = format(path)?;
+        // song paths are canonical, so the directory has to be too
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        }
+        .canonicalize()
+        .ok();
+        let entries: Vec<Entry> = self
+            .paths
+            .iter()
+            .map(|song_path| {
+                let relative = dir
+                    .as_ref()
+                    .and_then(|dir| Path::new(song_path).strip_prefix(dir).ok());
+                let location = match relative {
+                    Some(relative) => relative.to_string_lossy().into_owned(),
+                    None => song_path.to_owned(),
+                };
+                let song = library.song(song_path);
+                Entry {
+                    location,
+                    artist: song.map(|song| song.track_artist.to_owned()),
+                    title: song.map(|song| song.title.to_owned()),
+                    album: song.map(|song| song.album_title.to_owned()),
+                    duration_ms: song.map(|song| song.duration_ms),
+                }
+            })
+            .collect();
+        fs::write(path, format.write(&self.name, &entries)).map_err(|source| PlaylistError::Io {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+}
+
+// Every playlist, saved to the data directory after each change
+#[derive(Default, Serialize, Deserialize)]
+pub struct Playlists {
+    #[serde(skip)]
+    path: PathBuf,
+    playlists: Vec<Playlist>,
+}
+
+impl Playlists {
+    pub fn load() -> Playlists {
+        let path = data_dir().join("playlists.toml");
+        let mut playlists = match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).unwrap_or_else(|err| {
+                error!("Could not parse {}: {}", path.display(), err);
+                Playlists::default()
+            }),
+            Err(_) => Playlists::default(),
+        };
+        playlists.path = path;
+        for playlist in playlists.playlists.iter() {
+            if let Some(Err(err)) = playlist.rules.as_deref().map(SmartRules::parse) {
+                error!("Smart playlist \"{}\": {}", playlist.name, err);
+            }
+        }
+        playlists
+    }
+
+    pub fn list(&self) -> &[Playlist] {
+        &self.playlists
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&Playlist> {
+        self.playlists.get(idx)
+    }
+
+    // add an imported playlist, numbering its name if it's already used
+    pub fn add(&mut self, mut playlist: Playlist) -> Result<(), PlaylistError> {
+        let base = match playlist.name.trim() {
+            "" => "Imported".to_string(),
+            name => name.to_string(),
+        };
+        playlist.name = base.clone();
+        let mut number = 1;
+        while self.check_name(&playlist.name).is_err() {
+            number += 1;
+            playlist.name = format!("{} ({})", base, number);
+        }
+        self.playlists.push(playlist);
+        self.save()
+    }
+
+    pub fn create(&mut self, name: &str) -> Result<(), PlaylistError> {
+        let name = self.check_name(name)?;
+        self.playlists.push(Playlist::new(name));
+        self.save()
+    }
+
+    pub fn create_smart(
+        &mut self,
+        name: &str,
+        rules: &str,
+        library: &Library,
+    ) -> Result<(), PlaylistError> {
+        let name = self.check_name(name)?;
+        let smart_rules = SmartRules::parse(rules)?;
+        let seed = rand::random();
+        self.playlists.push(Playlist {
+            name,
+            paths: smart_rules.evaluate(library, seed),
+            rules: Some(rules.trim().to_string()),
+            seed,
+            from_config: false,
+        });
+        self.save()
+    }
+
+    // Adds a smart playlist from config.toml, replacing the rules of a saved one with the same
+    // name. It isn't saved, so changes to the config apply on the next start.
+    pub fn add_from_config(&mut self, name: &str, rules: &str) -> Result<(), PlaylistError> {
+        SmartRules::parse(rules)?;
+        let idx = match self.playlists.iter().position(|p| p.name == name.trim()) {
+            Some(idx) => idx,
+            None => {
+                let name = self.check_name(name)?;
+                self.playlists.push(Playlist::new(name));
+                self.playlists.len() - 1
+            }
+        };
+        self.playlists[idx].rules = Some(rules.trim().to_string());
+        self.playlists[idx].seed = rand::random();
+        self.playlists[idx].from_config = true;
+        Ok(())
+    }
+
+    pub fn set_rules(
+        &mut self,
+        idx: usize,
+        rules: &str,
+        library: &Library,
+    ) -> Result<(), PlaylistError> {
+        let smart_rules = SmartRules::parse(rules)?;
+        if let Some(playlist) = self.playlists.get_mut(idx) {
+            if playlist.from_config {
+                return Err(PlaylistError::FromConfig(playlist.name.to_owned()));
+            }
+            playlist.rules = Some(rules.trim().to_string());
+            // saving the same rules again reshuffles a random order
+            playlist.seed = rand::random();
+            playlist.paths = smart_rules.evaluate(library, playlist.seed);
+        }
+        self.save()
+    }
+
+    // re-evaluate the rules of every smart playlist
+    pub fn update_smart(&mut self, library: &Library) {
+        for playlist in self.playlists.iter_mut() {
+            // invalid rules are reported when loading
+            if let Some(Ok(rules)) = playlist.rules.as_deref().map(SmartRules::parse) {
+                playlist.paths = rules.evaluate(library, playlist.seed);
+            }
+        }
+    }
+
+    pub fn rename(&mut self, idx: usize, name: &str) -> Result<(), PlaylistError> {
+        self.check_not_from_config(idx)?;
+        if self
+            .playlists
+            .get(idx)
+            .is_some_and(|p| p.name == name.trim())
+        {
+            return Ok(());
+        }
+        let name = self.check_name(name)?;
+        if let Some(playlist) = self.playlists.get_mut(idx) {
+            playlist.name = name;
+        }
+        self.save()
+    }
+
+    pub fn delete(&mut self, idx: usize) -> Result<(), PlaylistError> {
+        self.check_not_from_config(idx)?;
+        if idx < self.playlists.len() {
+            self.playlists.remove(idx);
+        }
+        self.save()
+    }
+
+    pub fn add_song(&mut self, idx: usize, song: &Song) -> Result<(), PlaylistError> {
+        if let Some(playlist) = self.editable(idx)? {
+            playlist.add_song(song);
+        }
+        self.save()
+    }
+
+    pub fn remove_song(&mut self, idx: usize, pos: usize) -> Result<(), PlaylistError> {
+        if let Some(playlist) = self.editable(idx)? {
+            if pos < playlist.paths.len() {
+                playlist.paths.remove(pos);
+            }
+        }
+        self.save()
+    }
+
+    // move the song at `from` to `to` within the playlist
+    pub fn move_song(&mut self, idx: usize, from: usize, to: usize) -> Result<(), PlaylistError> {
+        if let Some(playlist) = self.editable(idx)? {
+            if from < playlist.paths.len() && to < playlist.paths.len() {
+                let path = playlist.paths.remove(from);
+                playlist.paths.insert(to, path);
+            }
+        }
+        self.save()
+    }
+
+    // the playlist at `idx` if its tracks can be edited, smart playlists can't
+    fn editable(&mut self, idx: usize) -> Result<Option<&mut Playlist>, PlaylistError> {
+        match self.playlists.get_mut(idx) {
+            Some(playlist) if playlist.is_smart() => {
+                Err(PlaylistError::Smart(playlist.name.to_owned()))
+            }
+            playlist => Ok(playlist),
+        }
+    }
+
+    fn check_not_from_config(&self, idx: usize) -> Result<(), PlaylistError> {
+        match self.playlists.get(idx) {
+            Some(playlist) if playlist.from_config => {
+                Err(PlaylistError::FromConfig(playlist.name.to_owned()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // trimmed `name` if it's not empty or used by another playlist
+    pub fn check_name(&self, name: &str) -> Result<String, PlaylistError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(PlaylistError::EmptyName);
+        }
+        if self.playlists.iter().any(|p| p.name == name) {
+            return Err(PlaylistError::Exists(name.to_string()));
+        }
+        Ok(name.to_string())
+    }
+
+    fn save(&self) -> Result<(), PlaylistError> {
+        let to_error = |source| PlaylistError::Save {
+            path: self.path.display().to_string(),
+            source,
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(to_error)?;
+        }
+        // smart playlists only need their rules, and the ones from config.toml are left out
+        let saved = Playlists {
+            path: PathBuf::new(),
+            playlists: self
+                .playlists
+                .iter()
+                .filter(|playlist| !playlist.from_config)
+                .map(|playlist| Playlist {
+                    paths: match playlist.is_smart() {
+                        true => Vec::new(),
+                        false => playlist.paths.clone(),
+                    },
+                    ..playlist.clone()
+                })
+                .collect(),
+        };
+        // serializing plain strings can't fail
+        let text = toml::to_string(&saved).unwrap_or_default();
+        fs::write(&self.path, text).map_err(to_error)
+    }
+}
+
+fn format(path: &Path) -> Result<PlaylistFormat, PlaylistError> {
+    PlaylistFormat::from_path(path).ok_or_else(|| PlaylistError::UnsupportedFormat {
+        path: path.display().to_string(),
+    })
+}
+
+// `location` joined to `dir` if it's relative, without "." and ".." components
+fn resolve(dir: &Path, location: &str) -> String {
+    // Windows separators in playlists made on Windows
+    let location = location.replace('\\', "/");
+    let joined = dir.join(&location);
+    if let Ok(path) = joined.canonicalize() {
+        return path.to_string_lossy().into_owned();
+    }
+    let mut path = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                path.pop();
+            }
+            component => path.push(component),
+        }
+    }
+    path.to_string_lossy().into_owned()
+}
+
+// library song for a playlist entry, by path, then by the end of its path, then by artist and
+// title
+fn find_song<'a>(path: &str, entry: &Entry, library: &'a Library) -> Option<&'a Song> {
+    if let Some(song) = library.song(path) {
+        return Some(song);
+    }
+    // the file and its parent directories, e.g. "Artist/Album/01 Song.mp3"
+    let components: Vec<&str> = path.rsplit('/').filter(|c| !c.is_empty()).collect();
+    let shared = |song: &Song| {
+        song.path
+            .rsplit('/')
+            .zip(components.iter())
+            .take_while(|(a, b)| a == *b)
+            .count()
+    };
+    let by_path = library
+        .songs
+        .iter()
+        .map(|song| (shared(song), song))
+        .filter(|(shared, _)| *shared >= 2)
+        .max_by_key(|(shared, _)| *shared);
+    if let Some((_, song)) = by_path {
+        return Some(song);
+    }
+
+    let title = fuzzy::fold(entry.title.as_deref()?);
+    let artist = fuzzy::fold(entry.artist.as_deref()?);
+    let mut matches = library.songs.iter().filter(|song| {
+        fuzzy::fold(&song.title) == title
+            && (fuzzy::fold(&song.track_artist) == artist
+                || song.artists.iter().any(|a| fuzzy::fold(a) == artist))
+    });
+    let first = matches.next()?;
+    // prefer the song from the same album when there are several versions
+    match &entry.album {
+        Some(album) => {
+            let album = fuzzy::fold(album);
+            std::iter::once(first)
+                .chain(matches)
+                .find(|song| fuzzy::fold(&song.album_title) == album)
+                .or(Some(first))
+        }
+        None => Some(first),
+    }
+}
+
+// $XDG_DATA_HOME/splay, or the platform equivalent
+fn is_zero(seed: &u32) -> bool {
+    *seed == 0
+}
 
-    pub fn write_to_file() {}
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("splay")
 }
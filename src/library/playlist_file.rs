@@ -0,0 +1,548 @@
+use std::path::Path;
+
+// One entry of a playlist file. `location` is the path as written in the file (absolute or
+// relative to it), file URLs are converted to paths when reading and back when writing XSPF.
+#[derive(Default)]
+pub struct Entry {
+    pub location: String,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub album: Option<String>,
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaylistFormat {
+    M3u,
+    Pls,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    pub fn from_path(path: &Path) -> Option<PlaylistFormat> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "m3u" | "m3u8" => Some(PlaylistFormat::M3u),
+            "pls" => Some(PlaylistFormat::Pls),
+            "xspf" => Some(PlaylistFormat::Xspf),
+            _ => None,
+        }
+    }
+
+    pub fn parse(&self, text: &str) -> Vec<Entry> {
+        match self {
+            PlaylistFormat::M3u => parse_m3u(text),
+            PlaylistFormat::Pls => parse_pls(text),
+            PlaylistFormat::Xspf => parse_xspf(text),
+        }
+    }
+
+    pub fn write(&self, title: &str, entries: &[Entry]) -> String {
+        match self {
+            PlaylistFormat::M3u => write_m3u(entries),
+            PlaylistFormat::Pls => write_pls(entries),
+            PlaylistFormat::Xspf => write_xspf(title, entries),
+        }
+    }
+}
+
+// Playlist files are usually UTF-8, but plain .m3u files are often Latin-1
+pub fn decode(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|b| *b as char).collect(),
+    }
+}
+
+// "Artist - Title" as used in #EXTINF and PLS titles
+fn split_display_title(title: &str) -> (Option<String>, Option<String>) {
+    match title.split_once(" - ") {
+        Some((artist, title)) => (
+            Some(artist.trim().to_string()),
+            Some(title.trim().to_string()),
+        ),
+        None if title.trim().is_empty() => (None, None),
+        None => (None, Some(title.trim().to_string())),
+    }
+}
+
+fn display_title(entry: &Entry) -> String {
+    match (&entry.artist, &entry.title) {
+        (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+        (None, Some(title)) => title.to_owned(),
+        _ => String::new(),
+    }
+}
+
+fn parse_m3u(text: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut info: Option<Entry> = None;
+    for line in text.lines().map(str::trim) {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            // #EXTINF:<seconds>[ attributes],<artist> - <title>
+            let (length, title) = extinf.split_once(',').unwrap_or((extinf, ""));
+            let secs = length
+                .split_whitespace()
+                .next()
+                .and_then(|secs| secs.parse::<i64>().ok());
+            let (artist, title) = split_display_title(title);
+            info = Some(Entry {
+                artist,
+                title,
+                duration_ms: secs
+                    .filter(|secs| *secs >= 0)
+                    .map(|secs| secs as u64 * 1000),
+                ..Default::default()
+            });
+        } else if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else {
+            let mut entry = info.take().unwrap_or_default();
+            entry.location = url_to_path(line);
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+fn write_m3u(entries: &[Entry]) -> String {
+    let mut text = String::from("#EXTM3U\n");
+    for entry in entries {
+        let secs = entry
+            .duration_ms
+            .map_or(-1, |ms| (ms as f64 / 1000.0).round() as i64);
+        text.push_str(&format!("#EXTINF:{},{}\n", secs, display_title(entry)));
+        text.push_str(&entry.location);
+        text.push('\n');
+    }
+    text
+}
+
+fn parse_pls(text: &str) -> Vec<Entry> {
+    // entries are numbered (File1, Title1, Length1, ...) and may come in any order
+    let mut entries: Vec<(u32, Entry)> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+            None => continue,
+        };
+        let field_end = key
+            .find(|ch: char| ch.is_ascii_digit())
+            .unwrap_or(key.len());
+        let number: u32 = match key[field_end..].parse() {
+            Ok(number) => number,
+            Err(_) => continue,
+        };
+        let idx = match entries.iter().position(|(n, _)| *n == number) {
+            Some(idx) => idx,
+            None => {
+                entries.push((number, Entry::default()));
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[idx].1;
+        match &key[..field_end] {
+            "file" => entry.location = url_to_path(value),
+            "title" => (entry.artist, entry.title) = split_display_title(value),
+            "length" => {
+                entry.duration_ms = value
+                    .parse::<i64>()
+                    .ok()
+                    .filter(|secs| *secs >= 0)
+                    .map(|secs| secs as u64 * 1000)
+            }
+            _ => (),
+        }
+    }
+    entries.sort_by_key(|(number, _)| *number);
+    entries
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .filter(|entry| !entry.location.is_empty())
+        .collect()
+}
+
+fn write_pls(entries: &[Entry]) -> String {
+    let mut text = String::from("[playlist]\n");
+    for (idx, entry) in entries.iter().enumerate() {
+        let number = idx + 1;
+        text.push_str(&format!("File{}={}\n", number, entry.location));
+        text.push_str(&format!("Title{}={}\n", number, display_title(entry)));
+        let secs = entry
+            .duration_ms
+            .map_or(-1, |ms| (ms as f64 / 1000.0).round() as i64);
+        text.push_str(&format!("Length{}={}\n", number, secs));
+    }
+    text.push_str(&format!("NumberOfEntries={}\nVersion=2\n", entries.len()));
+    text
+}
+
+// Only the parts of XSPF splay uses are read: the location, creator, title, album and duration
+// of each track
+fn parse_xspf(text: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("<track>") {
+        let end = match rest[start..].find("</track>") {
+            Some(end) => start + end,
+            None => break,
+        };
+        let track = &rest[start..end];
+        rest = &rest[end..];
+        // locations are URIs, relative ones are percent-encoded paths
+        let location = match element(track, "location") {
+            Some(location) if location.contains("://") => url_to_path(&location),
+            Some(location) => percent_decode(&location),
+            None => continue,
+        };
+        entries.push(Entry {
+            location,
+            artist: element(track, "creator"),
+            title: element(track, "title"),
+            album: element(track, "album"),
+            duration_ms: element(track, "duration").and_then(|ms| ms.parse().ok()),
+        });
+    }
+    entries
+}
+
+fn write_xspf(title: &str, entries: &[Entry]) -> String {
+    let mut text = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    text.push_str("<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+    text.push_str(&format!("  <title>{}</title>\n", escape_xml(title)));
+    text.push_str("  <trackList>\n");
+    for entry in entries {
+        text.push_str("    <track>\n");
+        text.push_str(&format!(
+            "      <location>{}</location>\n",
+            escape_xml(&path_to_uri(&entry.location))
+        ));
+        let fields = [
+            ("creator", entry.artist.clone()),
+            ("title", entry.title.clone()),
+            ("album", entry.album.clone()),
+            ("duration", entry.duration_ms.map(|ms| ms.to_string())),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                text.push_str(&format!("      <{0}>{1}</{0}>\n", name, escape_xml(&value)));
+            }
+        }
+        text.push_str("    </track>\n");
+    }
+    text.push_str("  </trackList>\n</playlist>\n");
+    text
+}
+
+// unescaped text of the first `<name>` element in `xml`
+fn element(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{}>", name);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&format!("</{}>", name))?;
+    let value = unescape_xml(xml[start..end].trim());
+    match value.is_empty() {
+        true => None,
+        false => Some(value),
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn unescape_xml(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let ch = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match ch {
+            Some(ch) => {
+                unescaped.push(ch);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+// Path of a file:// URL, other locations are returned as they are
+fn url_to_path(location: &str) -> String {
+    let path = match location.strip_prefix("file://") {
+        // file:///music/a.mp3 or file://localhost/music/a.mp3
+        Some(rest) => match rest.find('/') {
+            Some(start) => &rest[start..],
+            None => rest,
+        },
+        None => return location.to_string(),
+    };
+    percent_decode(path)
+}
+
+// file:// URL for absolute paths, relative paths are only percent-encoded
+fn path_to_uri(path: &str) -> String {
+    match path.starts_with('/') {
+        true => format!("file://{}", percent_encode(path)),
+        false => percent_encode(path),
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let hex = bytes
+            .get(idx + 1..idx + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[idx], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// everything but unreserved characters and '/'
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(location: &str, artist: Option<&str>, title: Option<&str>) -> Entry {
+        Entry {
+            location: location.to_string(),
+            artist: artist.map(str::to_string),
+            title: title.map(str::to_string),
+            album: Some("Album & <Co>".to_string()),
+            duration_ms: Some(215_000),
+        }
+    }
+
+    type Fields = (
+        String,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<u64>,
+    );
+
+    fn fields(entries: &[Entry]) -> Vec<Fields> {
+        entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.location.to_owned(),
+                    entry.artist.to_owned(),
+                    entry.title.to_owned(),
+                    entry.album.to_owned(),
+                    entry.duration_ms,
+                )
+            })
+            .collect()
+    }
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            entry(
+                "/music/Sigur Rós/100% (live).flac",
+                Some("Sigur Rós"),
+                Some("Hoppípolla"),
+            ),
+            entry("relative/a b.mp3", None, Some("Only - a title")),
+            Entry {
+                location: "/music/untitled.ogg".to_string(),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn formats_are_read_from_the_extension() {
+        let format = |path: &str| PlaylistFormat::from_path(Path::new(path));
+        assert_eq!(format("a/b.M3U8"), Some(PlaylistFormat::M3u));
+        assert_eq!(format("b.pls"), Some(PlaylistFormat::Pls));
+        assert_eq!(format("b.xspf"), Some(PlaylistFormat::Xspf));
+        assert_eq!(format("b.txt"), None);
+        assert_eq!(format("m3u"), None);
+    }
+
+    #[test]
+    fn xspf_round_trip() {
+        let text = PlaylistFormat::Xspf.write("Mine & yours", &entries());
+        assert!(text.contains("<title>Mine &amp; yours</title>"));
+        assert!(text.contains("file:///music/Sigur%20R%C3%B3s/100%25%20%28live%29.flac"));
+        assert!(text.contains("<location>relative/a%20b.mp3</location>"));
+        let parsed = PlaylistFormat::Xspf.parse(&text);
+        assert_eq!(fields(&parsed), fields(&entries()));
+    }
+
+    #[test]
+    fn m3u_and_pls_round_trip_without_albums() {
+        let mut expected = entries();
+        for entry in &mut expected {
+            entry.album = None;
+        }
+        // a title with " - " in it reads back as artist and title
+        expected[1].artist = Some("Only".to_string());
+        expected[1].title = Some("a title".to_string());
+        for format in [PlaylistFormat::M3u, PlaylistFormat::Pls] {
+            let parsed = format.parse(&format.write("unused", &entries()));
+            assert_eq!(fields(&parsed), fields(&expected), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn m3u_extinf_variants() {
+        let text = "#EXTM3U\n\
+            #EXTINF:120\n\
+            /music/no comma.mp3\n\
+            \n\
+            #EXTINF:-1 tvg-id=\"x\",Artist - Title\n\
+            #EXTVLCOPT:network-caching=1000\n\
+            file://localhost/music/a%20b.mp3\n\
+            # a comment\n\
+            http://radio.example/stream\n";
+        let parsed = PlaylistFormat::M3u.parse(text);
+        assert_eq!(
+            fields(&parsed),
+            vec![
+                (
+                    "/music/no comma.mp3".to_string(),
+                    None,
+                    None,
+                    None,
+                    Some(120_000)
+                ),
+                (
+                    "/music/a b.mp3".to_string(),
+                    Some("Artist".to_string()),
+                    Some("Title".to_string()),
+                    None,
+                    None
+                ),
+                (
+                    "http://radio.example/stream".to_string(),
+                    None,
+                    None,
+                    None,
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn pls_entries_are_ordered_by_number() {
+        let text = "[playlist]\n\
+            Title2=Second\n\
+            File2=file:///music/2.mp3\n\
+            file1 = /music/1.mp3\n\
+            Length1=-1\n\
+            Title3=no file\n\
+            NumberOfEntries=2\n";
+        let parsed = PlaylistFormat::Pls.parse(text);
+        assert_eq!(
+            fields(&parsed),
+            vec![
+                ("/music/1.mp3".to_string(), None, None, None, None),
+                (
+                    "/music/2.mp3".to_string(),
+                    None,
+                    Some("Second".to_string()),
+                    None,
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn xml_entities_are_unescaped() {
+        assert_eq!(
+            unescape_xml("a &amp; b &lt;&gt;&quot;&apos;"),
+            "a & b <>\"'"
+        );
+        assert_eq!(unescape_xml("&#xE9;t&#233; &#x1F3B5;"), "été 🎵");
+        // unknown or unfinished entities are kept
+        assert_eq!(unescape_xml("&nbsp; &#xZZ; R&B"), "&nbsp; &#xZZ; R&B");
+        let text = "<track><location>/music/caf&#xE9;.mp3</location>\
+            <title>  </title><duration>x</duration></track><track></track>";
+        let parsed = PlaylistFormat::Xspf.parse(text);
+        assert_eq!(
+            fields(&parsed),
+            vec![("/music/café.mp3".to_string(), None, None, None, None)]
+        );
+    }
+
+    #[test]
+    fn urls_and_percent_encoding() {
+        assert_eq!(url_to_path("file:///music/a%20b.mp3"), "/music/a b.mp3");
+        assert_eq!(
+            url_to_path("file://localhost/music/%C3%A9.mp3"),
+            "/music/é.mp3"
+        );
+        assert_eq!(url_to_path("/music/100%.mp3"), "/music/100%.mp3");
+        assert_eq!(percent_decode("100%2"), "100%2");
+        assert_eq!(percent_decode("%zz%41"), "%zzA");
+        assert_eq!(path_to_uri("/a b/é~.mp3"), "file:///a%20b/%C3%A9~.mp3");
+        assert_eq!(path_to_uri("a#b.mp3"), "a%23b.mp3");
+    }
+
+    #[test]
+    fn latin1_and_bom_are_decoded() {
+        assert_eq!(decode(b"/music/caf\xE9.mp3"), "/music/café.mp3");
+        assert_eq!(
+            decode("\u{FEFF}/music/café.mp3".as_bytes()),
+            "/music/café.mp3"
+        );
+        let parsed = PlaylistFormat::M3u.parse(&decode(b"#EXTINF:3,Bj\xF6rk - J\xF3ga\n/j.mp3\n"));
+        assert_eq!(parsed[0].artist.as_deref(), Some("Björk"));
+        assert_eq!(parsed[0].title.as_deref(), Some("Jóga"));
+    }
+}
@@ -0,0 +1,88 @@
+use lofty::{FileType, TaggedFile};
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::codecs::CODEC_TYPE_NULL;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+// Technical details of the audio stream
+#[derive(Debug, Default)]
+pub struct AudioProperties {
+    pub duration_ms: u64,
+    // kbps
+    pub bitrate: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u8>,
+    pub codec: Option<String>,
+}
+
+// Properties computed by lofty while reading the file (Xing/VBRI headers are used for VBR MP3s)
+pub fn from_lofty(file: &TaggedFile) -> AudioProperties {
+    let properties = file.properties();
+    AudioProperties {
+        duration_ms: properties.duration().as_millis() as u64,
+        bitrate: properties.audio_bitrate().or(properties.overall_bitrate()),
+        sample_rate: properties.sample_rate(),
+        channels: properties.channels(),
+        codec: Some(codec_name(file.file_type()).to_string()),
+    }
+}
+
+// Fallback for files lofty couldn't determine the duration of: the demuxer's frame count
+// divided by the sample rate
+pub fn from_symphonia(path: &str) -> Option<AudioProperties> {
+    let mut hint = Hint::new();
+    if let Some(extension) = Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+    let source = MediaSourceStream::new(Box::new(File::open(path).ok()?), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+    let track = probed
+        .format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)?;
+    let params = &track.codec_params;
+
+    let duration_ms = match (params.n_frames, params.time_base, params.sample_rate) {
+        (Some(frames), Some(time_base), _) => {
+            let time = time_base.calc_time(frames);
+            time.seconds * 1000 + (time.frac * 1000.0) as u64
+        }
+        (Some(frames), None, Some(rate)) if rate > 0 => frames * 1000 / rate as u64,
+        _ => 0,
+    };
+    let codec = symphonia::default::get_codecs()
+        .get_codec(params.codec)
+        .map(|codec| codec.short_name.to_uppercase());
+
+    Some(AudioProperties {
+        duration_ms,
+        bitrate: None,
+        sample_rate: params.sample_rate,
+        channels: params.channels.map(|channels| channels.count() as u8),
+        codec,
+    })
+}
+
+fn codec_name(file_type: &FileType) -> &'static str {
+    match file_type {
+        FileType::AIFF => "AIFF",
+        FileType::APE => "APE",
+        FileType::FLAC => "FLAC",
+        FileType::MP3 => "MP3",
+        FileType::MP4 => "AAC",
+        FileType::Opus => "Opus",
+        FileType::Vorbis => "Vorbis",
+        FileType::WAV => "PCM",
+    }
+}
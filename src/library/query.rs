@@ -0,0 +1,459 @@
+use std::str::FromStr;
+
+use super::errors::QueryError;
+use super::fuzzy;
+use super::song::Song;
+use super::unix_time;
+
+const DAY: u64 = 24 * 3600;
+
+#[derive(Clone, Copy, Debug)]
+enum TextField {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Genre,
+    Composer,
+    Codec,
+    Path,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum NumberField {
+    Year,
+    Plays,
+    Track,
+    Disc,
+    // seconds
+    Duration,
+    // kbps
+    Bitrate,
+    // stars, 0 when unrated
+    Rating,
+}
+
+// timestamps, compared by how long ago they are
+#[derive(Clone, Copy, Debug)]
+enum DateField {
+    Added,
+    LastPlayed,
+}
+
+// inclusive bounds, None is unbounded
+#[derive(Clone, Debug)]
+struct Range {
+    min: Option<u64>,
+    max: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+enum Condition {
+    Text(TextField, String),
+    Number(NumberField, Range),
+    // age range in seconds
+    Age(DateField, Range),
+    Loved(bool),
+    // a plain word, only used for negated words as the others are matched fuzzily
+    Any(String),
+}
+
+#[derive(Clone, Debug)]
+struct Filter {
+    negated: bool,
+    condition: Condition,
+}
+
+// A parsed library query like `artist:radiohead year:1995..2000 genre:rock plays:>5 -live`.
+// `field:value` terms filter on metadata, `-` negates a term, values can be quoted
+// (`album:"ok computer"`) and the remaining words are matched by the fuzzy search.
+// Dates are filtered by age, e.g. `added:<30d` or `lastplayed:>1y`, and `loved:yes` or
+// `rating:>=4` by what was set in splay.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    filters: Vec<Filter>,
+    // free text words, already folded
+    pub words: Vec<Vec<char>>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Query, QueryError> {
+        let mut query = Query::default();
+        for token in tokenize(input)? {
+            let condition = match token.field {
+                Some(field) => parse_condition(&field, &token.value)?,
+                None if token.negated => Condition::Any(fuzzy::fold(&token.value)),
+                None => {
+                    query.words.extend(fuzzy::query_words(&token.value));
+                    continue;
+                }
+            };
+            query.filters.push(Filter {
+                negated: token.negated,
+                condition,
+            });
+        }
+        Ok(query)
+    }
+
+    // whether `song` passes every field filter, free text words aren't checked here
+    pub fn matches(&self, song: &Song) -> bool {
+        self.filters
+            .iter()
+            .all(|filter| filter.condition.matches(song) != filter.negated)
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Query::parse(s)
+    }
+}
+
+impl Condition {
+    fn matches(&self, song: &Song) -> bool {
+        match self {
+            Condition::Text(field, value) => field
+                .values(song)
+                .iter()
+                .any(|v| fuzzy::fold(v).contains(value.as_str())),
+            Condition::Number(field, range) => field.value(song).is_some_and(|n| range.contains(n)),
+            Condition::Age(field, range) => field
+                .value(song)
+                .is_some_and(|time| range.contains(unix_time().saturating_sub(time))),
+            Condition::Loved(loved) => song.loved == *loved,
+            Condition::Any(value) => [
+                TextField::Title,
+                TextField::Artist,
+                TextField::Album,
+                TextField::Genre,
+            ]
+            .iter()
+            .flat_map(|field| field.values(song))
+            .any(|v| fuzzy::fold(v).contains(value.as_str())),
+        }
+    }
+}
+
+impl TextField {
+    fn values<'a>(&self, song: &'a Song) -> Vec<&'a str> {
+        match self {
+            TextField::Title => vec![&song.title],
+            TextField::Artist => song.artists.iter().map(String::as_str).collect(),
+            TextField::Album => vec![&song.album_title],
+            TextField::AlbumArtist => vec![&song.album_artist],
+            TextField::Genre => song.genres.iter().map(String::as_str).collect(),
+            TextField::Composer => song.composer.as_deref().into_iter().collect(),
+            TextField::Codec => song.codec.as_deref().into_iter().collect(),
+            TextField::Path => vec![&song.path],
+        }
+    }
+}
+
+impl NumberField {
+    fn value(&self, song: &Song) -> Option<u64> {
+        match self {
+            NumberField::Year => song.year.as_deref().and_then(leading_number),
+            NumberField::Plays => Some(song.play_count as u64),
+            NumberField::Track => song.track_number.as_deref().and_then(leading_number),
+            NumberField::Disc => song.disc_number.map(u64::from),
+            NumberField::Duration => Some(song.duration_ms / 1000),
+            NumberField::Bitrate => song.bitrate.map(u64::from),
+            NumberField::Rating => Some(song.rating as u64),
+        }
+    }
+}
+
+impl DateField {
+    fn value(&self, song: &Song) -> Option<u64> {
+        match self {
+            // songs imported before the date was recorded have no date
+            DateField::Added => Some(song.added).filter(|added| *added > 0),
+            DateField::LastPlayed => song.last_played,
+        }
+    }
+}
+
+impl Range {
+    fn contains(&self, n: u64) -> bool {
+        self.min.is_none_or(|min| n >= min) && self.max.is_none_or(|max| n <= max)
+    }
+}
+
+struct Token {
+    negated: bool,
+    field: Option<String>,
+    value: String,
+}
+
+// Splits on whitespace outside of quotes into `[-][field:]value` tokens
+fn tokenize(input: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.next_if(|ch| ch.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(tokens);
+        }
+        let negated = chars.next_if_eq(&'-').is_some();
+        let mut field = None;
+        let mut value = String::new();
+        let mut quoted = false;
+        while let Some(ch) = chars.next_if(|ch| quoted || !ch.is_whitespace()) {
+            match ch {
+                '"' => quoted = !quoted,
+                ':' if !quoted && field.is_none() && !value.is_empty() => {
+                    field = Some(std::mem::take(&mut value).to_lowercase());
+                }
+                _ => value.push(ch),
+            }
+        }
+        if quoted {
+            return Err(QueryError::UnclosedQuote);
+        }
+        // a lone "-" or "field:" that is still being typed
+        if value.is_empty() {
+            continue;
+        }
+        tokens.push(Token {
+            negated,
+            field,
+            value,
+        });
+    }
+}
+
+fn parse_condition(field: &str, value: &str) -> Result<Condition, QueryError> {
+    let text = |field| Ok(Condition::Text(field, fuzzy::fold(value)));
+    let invalid = || QueryError::InvalidNumber {
+        field: field.to_string(),
+        value: value.to_string(),
+    };
+    let number = |number_field| match parse_range(value, parse_number) {
+        Some(range) => Ok(Condition::Number(number_field, range)),
+        None => Err(invalid()),
+    };
+    let age = |date_field| match parse_range(value, parse_age) {
+        Some(range) => Ok(Condition::Age(date_field, range)),
+        None => Err(invalid()),
+    };
+    match field {
+        "title" => text(TextField::Title),
+        "artist" => text(TextField::Artist),
+        "album" => text(TextField::Album),
+        "albumartist" => text(TextField::AlbumArtist),
+        "genre" => text(TextField::Genre),
+        "composer" => text(TextField::Composer),
+        "codec" => text(TextField::Codec),
+        "path" => text(TextField::Path),
+        "year" => number(NumberField::Year),
+        "plays" => number(NumberField::Plays),
+        "track" => number(NumberField::Track),
+        "disc" => number(NumberField::Disc),
+        "duration" => number(NumberField::Duration),
+        "bitrate" => number(NumberField::Bitrate),
+        "rating" => number(NumberField::Rating),
+        "loved" => match value.to_lowercase().as_str() {
+            "yes" | "true" | "1" => Ok(Condition::Loved(true)),
+            "no" | "false" | "0" => Ok(Condition::Loved(false)),
+            _ => Err(QueryError::InvalidFlag {
+                field: field.to_string(),
+                value: value.to_string(),
+            }),
+        },
+        "added" => age(DateField::Added),
+        "lastplayed" => age(DateField::LastPlayed),
+        _ => Err(QueryError::UnknownField(field.to_string())),
+    }
+}
+
+// "5", ">5", ">=5", "<5", "<=5", "1995..2000", "1995.." or "..2000"
+fn parse_range(value: &str, number: fn(&str) -> Option<u64>) -> Option<Range> {
+    let bound = |s: &str| match s.trim().is_empty() {
+        true => Some(None),
+        false => number(s).map(Some),
+    };
+    if let Some((min, max)) = value.split_once("..") {
+        return Some(Range {
+            min: bound(min)?,
+            max: bound(max)?,
+        });
+    }
+    let range = if let Some(n) = value.strip_prefix(">=") {
+        Range {
+            min: Some(number(n)?),
+            max: None,
+        }
+    } else if let Some(n) = value.strip_prefix("<=") {
+        Range {
+            min: None,
+            max: Some(number(n)?),
+        }
+    } else if let Some(n) = value.strip_prefix('>') {
+        Range {
+            min: Some(number(n)?.checked_add(1)?),
+            max: None,
+        }
+    } else if let Some(n) = value.strip_prefix('<') {
+        Range {
+            min: None,
+            max: Some(number(n)?.checked_sub(1)?),
+        }
+    } else {
+        let n = number(value)?;
+        Range {
+            min: Some(n),
+            max: Some(n),
+        }
+    };
+    Some(range)
+}
+
+fn parse_number(value: &str) -> Option<u64> {
+    value.trim().parse().ok()
+}
+
+// "12h", "30d", "2w", "6m" or "1y" in seconds, days if there is no unit
+fn parse_age(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let end = value
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(value.len());
+    let unit = match &value[end..] {
+        "h" => 3600,
+        "" | "d" => DAY,
+        "w" => 7 * DAY,
+        "m" => 30 * DAY,
+        "y" => 365 * DAY,
+        _ => return None,
+    };
+    value[..end].parse::<u64>().ok()?.checked_mul(unit)
+}
+
+// the number at the start of values like "2001-05-03" or "3/12"
+fn leading_number(value: &str) -> Option<u64> {
+    let end = value
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song() -> Song {
+        let mut song = Song::new("Paranoid Android".to_string(), "/music/ok.flac".to_string());
+        song.album_title = "OK Computer".to_string();
+        song.artists = vec!["Radiohead".to_string()];
+        song.genres = vec!["Alternative Rock".to_string()];
+        song.year = Some("1997-05-21".to_string());
+        song.track_number = Some("2/12".to_string());
+        song.play_count = 6;
+        song.duration_ms = 383_000;
+        song
+    }
+
+    fn matches(query: &str, song: &Song) -> bool {
+        Query::parse(query).unwrap().matches(song)
+    }
+
+    #[test]
+    fn quoted_values_keep_their_spaces() {
+        assert!(matches("album:\"ok computer\"", &song()));
+        assert!(matches("\"album\":ok", &song()));
+        assert!(!matches("album:\"kid a\"", &song()));
+        // the quote can start anywhere in the value
+        assert!(matches("genre:alternative\" rock\"", &song()));
+        assert!(matches!(
+            Query::parse("album:\"ok computer"),
+            Err(QueryError::UnclosedQuote)
+        ));
+    }
+
+    #[test]
+    fn words_are_left_to_the_fuzzy_search() {
+        let query = Query::parse("Paranoid  year:1997 Andróid").unwrap();
+        assert_eq!(query.words, fuzzy::query_words("paranoid android"));
+        // unfinished terms are ignored while typing
+        let query = Query::parse("- year: ").unwrap();
+        assert!(query.words.is_empty() && query.filters.is_empty());
+    }
+
+    #[test]
+    fn text_fields_match_folded_values() {
+        assert!(matches("ARTIST:radio title:andróid", &song()));
+        assert!(matches("genre:rock path:ok.flac", &song()));
+        assert!(!matches("composer:yorke", &song()));
+        assert!(!matches("-artist:radiohead", &song()));
+        assert!(matches("-live", &song()));
+        assert!(!matches("-computer", &song()));
+    }
+
+    #[test]
+    fn numbers_and_ranges() {
+        let song = song();
+        for query in ["year:1997", "year:1995..2000", "year:1997..", "year:..1997"] {
+            assert!(matches(query, &song), "{query}");
+        }
+        for query in [
+            "plays:>5",
+            "plays:>=6",
+            "plays:<=6",
+            "track:2",
+            "duration:>380",
+        ] {
+            assert!(matches(query, &song), "{query}");
+        }
+        for query in ["year:>1997", "plays:<6", "track:12", "rating:>0", "disc:1"] {
+            assert!(!matches(query, &song), "{query}");
+        }
+        // "<0" can't match anything
+        assert!(matches!(
+            Query::parse("plays:<0"),
+            Err(QueryError::InvalidNumber { .. })
+        ));
+        assert!(matches!(
+            Query::parse("year:199x"),
+            Err(QueryError::InvalidNumber { .. })
+        ));
+    }
+
+    #[test]
+    fn dates_are_filtered_by_age() {
+        let mut song = song();
+        // not imported with a date or never played
+        assert!(!matches("added:<30d", &song));
+        assert!(!matches("lastplayed:>1y", &song));
+        assert!(matches("-lastplayed:<1y", &song));
+
+        song.added = unix_time() - 2 * DAY;
+        song.last_played = Some(unix_time() - 400 * DAY);
+        assert!(matches("added:<30d added:<3 added:>=1d", &song));
+        assert!(!matches("added:<12h", &song));
+        assert!(matches("lastplayed:>1y lastplayed:2w..", &song));
+        assert!(!matches("lastplayed:<6m", &song));
+        assert!(matches!(
+            Query::parse("added:<30s"),
+            Err(QueryError::InvalidNumber { .. })
+        ));
+    }
+
+    #[test]
+    fn loved_flags_and_unknown_fields() {
+        let mut song = song();
+        assert!(matches("loved:no", &song));
+        song.loved = true;
+        assert!(matches("loved:YES -loved:false", &song));
+        assert!(matches!(
+            Query::parse("loved:maybe"),
+            Err(QueryError::InvalidFlag { .. })
+        ));
+        match Query::parse("Mood:calm") {
+            Err(QueryError::UnknownField(field)) => assert_eq!(field, "mood"),
+            other => panic!("{other:?}"),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use rand::seq::SliceRandom;
+use std::collections::HashSet;
+
+use super::song::Song;
+use super::{unix_time, Library};
+
+// only the best matches are picked from, at random so the same seed doesn't always give the
+// same songs
+const CANDIDATES: usize = 50;
+// songs played this recently are left out
+const RECENT_SECS: u64 = 24 * 3600;
+
+// Up to `count` songs similar to `seed` by artist, album artist, genre and year, favouring the
+// ones played most. Songs in `exclude` (paths) or played in the last day are skipped.
+pub fn similar<'a>(
+    library: &'a Library,
+    seed: &Song,
+    exclude: &HashSet<&str>,
+    count: usize,
+) -> Vec<&'a Song> {
+    let now = unix_time();
+    let mut candidates: Vec<(f64, &Song)> = library
+        .songs
+        .iter()
+        .filter(|song| song.path != seed.path && !exclude.contains(song.path.as_str()))
+        .filter(|song| {
+            song.last_played
+                .is_none_or(|played| now.saturating_sub(played) > RECENT_SECS)
+        })
+        .map(|song| (similarity(seed, song), song))
+        .collect();
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+    // nothing in common with the seed, any song will do
+    let similar = candidates
+        .iter()
+        .take_while(|(score, _)| *score > 0.0)
+        .count();
+    let candidates = match similar {
+        0 => &candidates[..],
+        n => &candidates[..n.min(CANDIDATES)],
+    };
+    candidates
+        .choose_multiple_weighted(&mut rand::thread_rng(), count, |(score, _)| score.max(0.1))
+        .map(|picked| picked.map(|(_, song)| *song).collect())
+        .unwrap_or_default()
+}
+
+fn similarity(seed: &Song, song: &Song) -> f64 {
+    let mut score = 0.0;
+    if song
+        .artists
+        .iter()
+        .any(|artist| seed.artists.contains(artist))
+    {
+        score += 4.0;
+    }
+    if !seed.album_artist.is_empty() && song.album_artist == seed.album_artist {
+        score += 3.0;
+    }
+    let shared_genres = song
+        .genres
+        .iter()
+        .filter(|genre| seed.genres.contains(genre))
+        .count();
+    score += 2.0 * shared_genres.min(2) as f64;
+    if let (Some(a), Some(b)) = (year(seed), year(song)) {
+        score += match a.abs_diff(b) {
+            0..=2 => 2.0,
+            3..=5 => 1.0,
+            _ => 0.0,
+        };
+    }
+    // only boost songs that are similar, so well liked but unrelated songs don't come up
+    if score > 0.0 {
+        score += (song.play_count as f64 + 1.0).ln();
+    }
+    score
+}
+
+fn year(song: &Song) -> Option<u32> {
+    let year = song.year.as_deref()?;
+    year.get(..4)?.parse().ok()
+}
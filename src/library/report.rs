@@ -0,0 +1,60 @@
+use std::fmt;
+
+use super::errors::ImportError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SkipReason {
+    NotAudio,
+    AlreadyImported,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotAudio => write!(f, "not an audio file"),
+            Self::AlreadyImported => write!(f, "already imported"),
+        }
+    }
+}
+
+// What happened to a single file during an import
+pub enum ImportStatus {
+    Imported,
+    Skipped(SkipReason),
+}
+
+// Summary of an import listing every file that was imported, skipped or failed
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: Vec<String>,
+    pub skipped: Vec<(String, SkipReason)>,
+    pub failed: Vec<ImportError>,
+}
+
+impl ImportReport {
+    pub fn record(&mut self, path: &str, result: Result<ImportStatus, ImportError>) {
+        match result {
+            Ok(ImportStatus::Imported) => self.imported.push(path.to_string()),
+            Ok(ImportStatus::Skipped(reason)) => self.skipped.push((path.to_string(), reason)),
+            Err(err) => self.failed.push(err),
+        }
+    }
+
+    pub fn merge(&mut self, other: ImportReport) {
+        self.imported.extend(other.imported);
+        self.skipped.extend(other.skipped);
+        self.failed.extend(other.failed);
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} imported, {} skipped, {} failed",
+            self.imported.len(),
+            self.skipped.len(),
+            self.failed.len()
+        )
+    }
+}
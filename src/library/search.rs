@@ -1,44 +1,313 @@
 // TODO: make SearchDB a trait and have different variations that implement it (songs, artists, albums, playlists, genres ...)
 
-struct node {
-    is_word: bool,
-    left_child: Box<node>,
-    middle_child: Box<node>,
-    right_child: Box<node>,
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::BufReader;
+
+use super::fuzzy;
+use super::song::Song;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Field {
+    Title,
+    Artist,
+    Album,
+}
+
+// Ternary search tree node, `matches` is set on nodes that end a key and points into `words`
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Node {
+    ch: char,
+    left_child: Option<Box<Node>>,
+    middle_child: Option<Box<Node>>,
+    right_child: Option<Box<Node>>,
+    matches: Vec<(Field, usize)>,
 }
 
+// Prefix search over song titles, artists and albums. Every word of a value is a key and
+// every word of a query has to match one of them, so "pol kar" finds "Karma Police".
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct SearchDB {
-    path_to_db: String,
     words: Vec<String>,
+    // number of songs using each (field, word), entries are removed from the tree at 0
+    counts: HashMap<(Field, usize), u32>,
+    #[serde(skip)]
+    word_ids: HashMap<String, usize>,
+    root: Option<Box<Node>>,
 }
 
 impl SearchDB {
     pub fn new() -> SearchDB {
-        SearchDB {
-            path_to_db: String::new(),
-            words: Vec::new(),
+        SearchDB::default()
+    }
+
+    pub fn build(songs: &[Song]) -> SearchDB {
+        let mut db = SearchDB::new();
+        for song in songs {
+            db.insert_song(song);
+        }
+        db
+    }
+
+    // (titles, artists, albums) that have a word starting with each word of `input`
+    pub fn find_matches(&self, input: &str) -> (Vec<String>, Vec<String>, Vec<String>) {
+        let mut titles = Vec::new();
+        let mut artists = Vec::new();
+        let mut albums = Vec::new();
+
+        let mut found: Option<Vec<(Field, usize)>> = None;
+        for word in words(input) {
+            let mut word_matches = Vec::new();
+            if let Some(node) = self.find_match(&word) {
+                word_matches.extend(node.matches.iter());
+                if let Some(child) = &node.middle_child {
+                    collect(child, &mut word_matches);
+                }
+            }
+            found = Some(match found {
+                Some(found) => {
+                    let word_matches: HashSet<_> = word_matches.into_iter().collect();
+                    found
+                        .into_iter()
+                        .filter(|m| word_matches.contains(m))
+                        .collect()
+                }
+                None => word_matches,
+            });
+        }
+        let mut found = found.unwrap_or_default();
+        found.sort_unstable_by_key(|(field, id)| (*field as u8, *id));
+        found.dedup();
+        for (field, id) in found {
+            let word = self.words[id].to_owned();
+            match field {
+                Field::Title => titles.push(word),
+                Field::Artist => artists.push(word),
+                Field::Album => albums.push(word),
+            }
+        }
+        (titles, artists, albums)
+    }
+
+    // node of the last character of `input`
+    fn find_match(&self, input: &str) -> Option<&Node> {
+        let chars: Vec<char> = input.chars().collect();
+        let mut node = self.root.as_deref()?;
+        let mut pos = 0;
+        loop {
+            let ch = chars[pos];
+            if ch < node.ch {
+                node = node.left_child.as_deref()?;
+            } else if ch > node.ch {
+                node = node.right_child.as_deref()?;
+            } else if pos + 1 == chars.len() {
+                return Some(node);
+            } else {
+                pos += 1;
+                node = node.middle_child.as_deref()?;
+            }
+        }
+    }
+
+    pub fn insert_song(&mut self, song: &Song) {
+        self.insert(Field::Title, &song.title);
+        for artist in song.artists.iter() {
+            self.insert(Field::Artist, artist);
+        }
+        self.insert(Field::Album, &song.album_title);
+    }
+
+    pub fn remove_song(&mut self, song: &Song) {
+        self.remove(Field::Title, &song.title);
+        for artist in song.artists.iter() {
+            self.remove(Field::Artist, artist);
+        }
+        self.remove(Field::Album, &song.album_title);
+    }
+
+    pub fn insert(&mut self, field: Field, input: &str) {
+        if words(input).is_empty() {
+            return;
+        }
+        let id = match self.word_ids.get(input) {
+            Some(id) => *id,
+            None => {
+                self.words.push(input.to_string());
+                self.word_ids
+                    .insert(input.to_string(), self.words.len() - 1);
+                self.words.len() - 1
+            }
+        };
+        let count = self.counts.entry((field, id)).or_insert(0);
+        *count += 1;
+        if *count > 1 {
+            return;
+        }
+        for word in words(input) {
+            let node = self.node_mut(&word);
+            node.matches.push((field, id));
         }
     }
 
-    pub fn find_matches(self, input: String) -> (Vec<String>, Vec<String>, Vec<String>) {
-        return (Vec::new(), Vec::new(), Vec::new());
+    pub fn remove(&mut self, field: Field, input: &str) {
+        let id = match self.word_ids.get(input) {
+            Some(id) => *id,
+            None => return,
+        };
+        let count = match self.counts.get_mut(&(field, id)) {
+            Some(count) => count,
+            None => return,
+        };
+        *count -= 1;
+        if *count > 0 {
+            return;
+        }
+        self.counts.remove(&(field, id));
+        // nodes are left in place, they are cheap and likely to be reused
+        for word in words(input) {
+            let node = self.node_mut(&word);
+            node.matches.retain(|m| *m != (field, id));
+        }
     }
 
-    fn find_match(self, input: String) {}
+    // node for the last character of `key`, creating the path to it as needed
+    fn node_mut(&mut self, key: &str) -> &mut Node {
+        let chars: Vec<char> = key.chars().collect();
+        let mut link = &mut self.root;
+        let mut pos = 0;
+        loop {
+            let ch = chars[pos];
+            let node = link.get_or_insert_with(|| {
+                Box::new(Node {
+                    ch,
+                    ..Default::default()
+                })
+            });
+            if ch < node.ch {
+                link = &mut node.left_child;
+            } else if ch > node.ch {
+                link = &mut node.right_child;
+            } else if pos + 1 == chars.len() {
+                return node;
+            } else {
+                pos += 1;
+                link = &mut node.middle_child;
+            }
+        }
+    }
 
-    pub fn insert(self, input: String) -> Result<(), String> {
-        return Ok(());
+    // saved with a checksum of the indexed values of `songs`, see `load`
+    pub fn save(&self, path: &str, songs: &[Song]) -> Result<(), Box<dyn Error>> {
+        let file = fs::File::create(path)?;
+        bincode::serialize_into(&file, &checksum(songs))?;
+        bincode::serialize_into(&file, self)?;
+        Ok(())
     }
 
-    fn remove(self, input: String) -> Result<(), String> {
-        return Ok(());
+    // Fails if the index wasn't saved for `songs`, e.g. after the library couldn't be saved
+    pub fn load(path: &str, songs: &[Song]) -> Result<SearchDB, Box<dyn Error>> {
+        let mut reader = BufReader::new(fs::File::open(path)?);
+        let saved: u64 = bincode::deserialize_from(&mut reader)?;
+        if saved != checksum(songs) {
+            return Err("it doesn't match the library".into());
+        }
+        let mut db: SearchDB = bincode::deserialize_from(reader)?;
+        db.word_ids = db
+            .words
+            .iter()
+            .enumerate()
+            .map(|(id, word)| (word.to_owned(), id))
+            .collect();
+        Ok(db)
     }
+}
 
-    fn save(self) -> Result<(), String> {
-        unimplemented!()
+fn checksum(songs: &[Song]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for song in songs {
+        (&song.title, &song.artists, &song.album_title).hash(&mut hasher);
     }
+    hasher.finish()
+}
+
+// lowercased words of `input` without diacritics
+fn words(input: &str) -> Vec<String> {
+    let mut words: Vec<String> = fuzzy::fold(input)
+        .split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_string())
+        .collect();
+    words.sort_unstable();
+    words.dedup();
+    words
+}
+
+// every match in the subtree under `node`
+fn collect(node: &Node, found: &mut Vec<(Field, usize)>) {
+    // iterative to not overflow the stack on long keys
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        found.extend(node.matches.iter());
+        for child in [&node.left_child, &node.middle_child, &node.right_child]
+            .into_iter()
+            .flatten()
+        {
+            stack.push(child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(title: &str, artist: &str, album: &str) -> Song {
+        let mut song = Song::new(title.to_string(), format!("/music/{}.mp3", title));
+        song.artists = vec![artist.to_string()];
+        song.album_title = album.to_string();
+        song
+    }
+
+    #[test]
+    fn words_match_by_prefix() {
+        let db = SearchDB::build(&[
+            song("Karma Police", "Radiohead", "OK Computer"),
+            song("Police Truck", "Dead Kennedys", "Give Me Convenience"),
+        ]);
+        let (titles, artists, albums) = db.find_matches("pol kar");
+        assert_eq!(titles, ["Karma Police"]);
+        assert!(artists.is_empty() && albums.is_empty());
+        let (titles, artists, _) = db.find_matches("DEAD");
+        assert!(titles.is_empty());
+        assert_eq!(artists, ["Dead Kennedys"]);
+    }
+
+    #[test]
+    fn removed_songs_stop_matching() {
+        let songs = [song("Airbag", "Radiohead", "OK Computer")];
+        let mut db = SearchDB::build(&songs);
+        db.remove_song(&songs[0]);
+        assert_eq!(db.find_matches("air"), (vec![], vec![], vec![]));
+    }
+
+    #[test]
+    fn index_saved_for_other_songs_is_not_loaded() {
+        let path = std::env::temp_dir()
+            .join(format!("splay-search-{}", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let mut songs = vec![song("Airbag", "Radiohead", "OK Computer")];
+        SearchDB::build(&songs).save(&path, &songs).unwrap();
+        let loaded = SearchDB::load(&path, &songs);
+        songs.push(song("Lucky", "Radiohead", "OK Computer"));
+        let stale = SearchDB::load(&path, &songs);
+        let _ = fs::remove_file(&path);
 
-    fn load() -> Result<(), String> {
-        unimplemented!()
+        assert_eq!(loaded.unwrap().find_matches("air").0, ["Airbag"]);
+        assert!(stale.is_err());
     }
 }
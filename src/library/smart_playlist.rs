@@ -0,0 +1,126 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use super::errors::QueryError;
+use super::query::Query;
+use super::song::Song;
+use super::Library;
+
+#[derive(Clone, Copy, Debug)]
+enum SortKey {
+    Title,
+    Artist,
+    Album,
+    Year,
+    Plays,
+    LastPlayed,
+    Added,
+    Duration,
+    Rating,
+    Random,
+}
+
+// Rules of a smart playlist: a library query plus `sort:<field>` (`sort:-<field>` for
+// descending) and `limit:<n>` terms, e.g. `lastplayed:<30d sort:-plays limit:50`
+#[derive(Clone, Debug)]
+pub struct SmartRules {
+    query: Query,
+    // key and whether it's descending
+    sort: Option<(SortKey, bool)>,
+    limit: Option<usize>,
+}
+
+impl SmartRules {
+    pub fn parse(rules: &str) -> Result<SmartRules, QueryError> {
+        let mut sort = None;
+        let mut limit = None;
+        let mut query = Vec::new();
+        for word in rules.split_whitespace() {
+            let lowercase = word.to_lowercase();
+            if let Some(key) = lowercase.strip_prefix("sort:") {
+                let (key, descending) = match key.strip_prefix('-') {
+                    Some(key) => (key, true),
+                    None => (key, false),
+                };
+                sort = Some((parse_sort_key(key)?, descending));
+            } else if let Some(n) = lowercase.strip_prefix("limit:") {
+                limit = Some(n.parse().map_err(|_| QueryError::InvalidNumber {
+                    field: "limit".to_string(),
+                    value: n.to_string(),
+                })?);
+            } else {
+                query.push(word);
+            }
+        }
+        Ok(SmartRules {
+            query: Query::parse(&query.join(" "))?,
+            sort,
+            limit,
+        })
+    }
+
+    // Paths of the matching songs, in order. `sort:random` orders them by `seed`, so the order
+    // only changes with the seed and songs that start or stop matching keep the others in place.
+    pub fn evaluate(&self, library: &Library, seed: u32) -> Vec<String> {
+        let mut songs: Vec<&Song> = library
+            .query(&self.query)
+            .into_iter()
+            .map(|hit| hit.song)
+            .collect();
+        match self.sort {
+            Some((SortKey::Random, _)) => songs.sort_by_cached_key(|song| {
+                let mut hasher = DefaultHasher::new();
+                (seed, &song.path).hash(&mut hasher);
+                hasher.finish()
+            }),
+            Some((key, descending)) => songs.sort_by(|a, b| {
+                let ordering = key.compare(a, b);
+                match descending {
+                    true => ordering.reverse(),
+                    false => ordering,
+                }
+            }),
+            None => (),
+        }
+        if let Some(limit) = self.limit {
+            songs.truncate(limit);
+        }
+        songs.into_iter().map(|song| song.path.to_owned()).collect()
+    }
+}
+
+impl SortKey {
+    fn compare(&self, a: &Song, b: &Song) -> Ordering {
+        match self {
+            SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            SortKey::Artist => (a.track_artist.to_lowercase(), a.track_position())
+                .cmp(&(b.track_artist.to_lowercase(), b.track_position())),
+            SortKey::Album => (a.album_title.to_lowercase(), a.track_position())
+                .cmp(&(b.album_title.to_lowercase(), b.track_position())),
+            SortKey::Year => a.year.cmp(&b.year),
+            SortKey::Plays => a.play_count.cmp(&b.play_count),
+            SortKey::LastPlayed => a.last_played.cmp(&b.last_played),
+            SortKey::Added => a.added.cmp(&b.added),
+            SortKey::Duration => a.duration_ms.cmp(&b.duration_ms),
+            SortKey::Rating => (a.rating, a.loved).cmp(&(b.rating, b.loved)),
+            SortKey::Random => Ordering::Equal,
+        }
+    }
+}
+
+fn parse_sort_key(key: &str) -> Result<SortKey, QueryError> {
+    match key {
+        "title" => Ok(SortKey::Title),
+        "artist" => Ok(SortKey::Artist),
+        "album" => Ok(SortKey::Album),
+        "year" => Ok(SortKey::Year),
+        "plays" => Ok(SortKey::Plays),
+        "lastplayed" => Ok(SortKey::LastPlayed),
+        "added" => Ok(SortKey::Added),
+        "duration" => Ok(SortKey::Duration),
+        "rating" => Ok(SortKey::Rating),
+        "random" => Ok(SortKey::Random),
+        _ => Err(QueryError::UnknownSort(key.to_string())),
+    }
+}
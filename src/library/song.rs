@@ -1,21 +1,58 @@
-use lofty::Tag;
+use lofty::{Tag, TagType};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
-use super::{errors::ImportError, tag};
+use super::errors::ImportError;
+use super::path_pattern::{self, PathPattern};
+use super::properties::AudioProperties;
+use super::tag::{self, TagFile};
+use super::user_data::UserData;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Song {
     pub title: String,
     pub album_title: String,
+    // artists as they should be displayed, e.g. "Artist A; Artist B"
     pub track_artist: String,
+    // every artist credited on the track
+    pub artists: Vec<String>,
     pub album_artist: String,
-    pub genre: Option<String>,
+    pub genres: Vec<String>,
     pub year: Option<String>,
-    pub duration_secs: u64,
+    pub duration_ms: u64,
+    // kbps
+    pub bitrate: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u8>,
+    pub codec: Option<String>,
     pub play_count: u32,
+    // seconds since the epoch of the last play and of the first import
+    pub last_played: Option<u64>,
+    pub added: u64,
+    // 0 to 5 stars, 0 when unrated
+    pub rating: u8,
+    pub loved: bool,
     pub track_number: Option<String>,
+    pub track_total: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub disc_total: Option<u32>,
+    pub composer: Option<String>,
+    pub conductor: Option<String>,
+    pub compilation: bool,
+    pub title_sort: Option<String>,
+    pub artist_sort: Option<String>,
+    pub album_sort: Option<String>,
+    pub album_artist_sort: Option<String>,
+    pub musicbrainz_recording_id: Option<String>,
+    pub musicbrainz_release_id: Option<String>,
     pub path: String,
+    // modification time of the file when it was read, used to pick up changes on rescan
+    pub modified: u64,
+    // key of the cover art thumbnail in the artwork cache
+    pub artwork: Option<String>,
+    // set when some of the metadata was guessed from the file path instead of read from tags
+    pub metadata_inferred: bool,
 }
 
 impl Song {
@@ -24,29 +61,194 @@ impl Song {
             title,
             album_title: tag::UNKNOWN_ALBUM.to_string(),
             track_artist: tag::UNKNOWN_ARTIST.to_string(),
+            artists: Vec::new(),
             album_artist: tag::UNKNOWN_ARTIST.to_string(),
-            genre: None,
+            genres: Vec::new(),
             year: None,
-            duration_secs: 0,
+            duration_ms: 0,
+            bitrate: None,
+            sample_rate: None,
+            channels: None,
+            codec: None,
             play_count: 0,
+            last_played: None,
+            added: 0,
+            rating: 0,
+            loved: false,
             track_number: None,
+            track_total: None,
+            disc_number: None,
+            disc_total: None,
+            composer: None,
+            conductor: None,
+            compilation: false,
+            title_sort: None,
+            artist_sort: None,
+            album_sort: None,
+            album_artist_sort: None,
+            musicbrainz_recording_id: None,
+            musicbrainz_release_id: None,
             path,
+            modified: 0,
+            artwork: None,
+            metadata_inferred: false,
         }
     }
 
-    pub fn from_tag(tag: &Tag, path: String) -> Result<Self, Box<ImportError>> {
-        let title = match tag::get_title(tag) {
-            Ok(title) => title,
-            Err(err) => return Err(err),
-        };
-        let mut s = Self::new(title, path);
+    // Missing fields (including the title) are guessed from the path using `patterns`.
+    // Artists and genres are split into multiple values on `separators`. `file` has the
+    // ID3v2.4 artists and genres lofty doesn't read all values of.
+    pub fn from_tag(
+        tag: &Tag,
+        file: &TagFile,
+        path: String,
+        patterns: &[PathPattern],
+        separators: &[String],
+    ) -> Result<Self, ImportError> {
+        let mut s = Self::new(tag::get_title(tag).unwrap_or_default(), path);
         s.track_artist = tag::get_track_artist(tag);
+        s.artists = tag::get_track_artists(tag, separators);
+        s.genres = tag::get_genres(tag, separators);
+        if *tag.tag_type() == TagType::Id3v2 {
+            let frames = &file.v24_text_frames;
+            if let Some(artists) = frames.get("TPE1") {
+                s.track_artist = artists.join("; ");
+                s.artists = tag::split_values(artists.iter().map(|a| a.as_str()), separators);
+            }
+            if let Some(genres) = frames.get("TCON") {
+                s.genres = tag::split_values(genres.iter().map(|g| g.as_str()), separators);
+            }
+        }
         s.album_title = tag::get_album_title(tag);
         s.album_artist = tag::get_album_artist(tag);
         s.year = tag::get_year(tag);
         s.track_number = tag::get_track_number(tag);
-        s.genre = tag::get_genre(tag);
-        s.duration_secs = tag::get_total_dur_sec(tag);
+        s.duration_ms = tag::get_length_ms(tag);
+        s.track_total = tag::get_track_total(tag);
+        s.disc_number = tag::get_disc_number(tag);
+        s.disc_total = tag::get_disc_total(tag);
+        s.composer = tag::get_composer(tag);
+        s.conductor = tag::get_conductor(tag);
+        s.compilation = tag::get_compilation(tag);
+        s.title_sort = tag::get_title_sort(tag);
+        s.artist_sort = tag::get_artist_sort(tag);
+        s.album_sort = tag::get_album_sort(tag);
+        s.album_artist_sort = tag::get_album_artist_sort(tag);
+        s.musicbrainz_recording_id = tag::get_musicbrainz_recording_id(tag);
+        s.musicbrainz_release_id = tag::get_musicbrainz_release_id(tag);
+        s.infer_missing(patterns)?;
+        s.fill_fallbacks();
+        Ok(s)
+    }
+
+    // Stream properties replace the length from tags, which is kept if the duration couldn't
+    // be computed
+    pub fn set_properties(&mut self, properties: AudioProperties) {
+        if properties.duration_ms > 0 {
+            self.duration_ms = properties.duration_ms;
+        }
+        self.bitrate = properties.bitrate;
+        self.sample_rate = properties.sample_rate;
+        self.channels = properties.channels;
+        self.codec = properties.codec;
+    }
+
+    // carry over data that isn't stored in the file when the song is read again
+    pub fn keep_user_data(&mut self, old: &Song) {
+        UserData::of(old).apply(self);
+    }
+
+    // (disc, track) position of the track on its album, used for ordering album tracks
+    pub fn track_position(&self) -> (u32, u32) {
+        // track numbers can be stored as "3", "03", "3/12" or "1-03" (disc-track)
+        let (disc, track) = match &self.track_number {
+            Some(track) => match track.split_once('-') {
+                Some((disc, track)) => (disc.trim().parse().ok(), track),
+                None => (None, track.as_str()),
+            },
+            None => (None, ""),
+        };
+        let track = track
+            .split('/')
+            .next()
+            .and_then(|t| t.trim().parse().ok())
+            .unwrap_or(0);
+        (self.disc_number.or(disc).unwrap_or(0), track)
+    }
+
+    // Songs without an album artist are grouped under their track artist, or under
+    // "Various Artists" when they are part of a compilation
+    fn fill_fallbacks(&mut self) {
+        if self.artists.is_empty() {
+            self.artists.push(self.track_artist.to_owned());
+        }
+        if self.album_artist != tag::UNKNOWN_ARTIST {
+            return;
+        }
+        self.album_artist = if self.compilation {
+            tag::VARIOUS_ARTISTS.to_string()
+        } else {
+            self.track_artist.to_owned()
+        };
+    }
+
+    // Used for files without any tags
+    pub fn from_path(path: String, patterns: &[PathPattern]) -> Result<Self, ImportError> {
+        let mut s = Self::new(String::new(), path);
+        s.infer_missing(patterns)?;
+        s.fill_fallbacks();
         Ok(s)
     }
+
+    // An empty list of patterns disables inference, in which case a title tag is required
+    fn infer_missing(&mut self, patterns: &[PathPattern]) -> Result<(), ImportError> {
+        if patterns.is_empty() {
+            if self.title.is_empty() {
+                return Err(ImportError::MissingTitle {
+                    path: self.path.to_owned(),
+                });
+            }
+            return Ok(());
+        }
+        let inferred = path_pattern::infer(Path::new(&self.path), patterns);
+
+        if self.title.is_empty() {
+            self.title = match inferred.title {
+                Some(title) => title,
+                None => Path::new(&self.path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            };
+            self.metadata_inferred = true;
+        }
+        if self.track_artist == tag::UNKNOWN_ARTIST {
+            if let Some(artist) = &inferred.artist {
+                self.track_artist = artist.to_owned();
+                self.artists = vec![artist.to_owned()];
+                self.metadata_inferred = true;
+            }
+        }
+        if self.album_artist == tag::UNKNOWN_ARTIST {
+            if let Some(artist) = inferred.album_artist.or(inferred.artist) {
+                self.album_artist = artist;
+                self.metadata_inferred = true;
+            }
+        }
+        if self.album_title == tag::UNKNOWN_ALBUM {
+            if let Some(album) = inferred.album {
+                self.album_title = album;
+                self.metadata_inferred = true;
+            }
+        }
+        if self.track_number.is_none() && inferred.track_number.is_some() {
+            self.track_number = inferred.track_number;
+            self.metadata_inferred = true;
+        }
+        if self.year.is_none() && inferred.year.is_some() {
+            self.year = inferred.year;
+            self.metadata_inferred = true;
+        }
+        Ok(())
+    }
 }
@@ -1,22 +1,37 @@
-use lofty::{ItemKey, Tag};
+use lofty::id3::v2::{
+    FrameValue, Id3v2Tag, SyncTextContentType, SynchronizedText, TimestampFormat,
+};
+use lofty::mp3::Mp3File;
+use lofty::{AudioFile, FileType, ItemKey, ItemValue, LoftyError, Probe, Tag, TaggedFile};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
 use std::time::Duration;
 
-use super::errors::ImportError;
+use super::id3;
 
 pub const UNKNOWN_ARTIST: &str = "Unknown Artist";
 pub const UNKNOWN_ALBUM: &str = "Unkwon Album";
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
+// separators used to split artist and genre tags when none are configured
+pub const DEFAULT_SEPARATORS: [&str; 3] = [";", " / ", " feat. "];
 
-pub fn get_title(tag: &Tag) -> Result<String, Box<ImportError>> {
-    match tag.get_string(&ItemKey::TrackTitle) {
-        Some(title) => Ok(title.to_string()),
-        None => return Err(Box::new(ImportError::MissingData)),
-    }
+// Keys used by Vorbis comments, APE and MP4 freeform atoms for MusicBrainz IDs
+const MUSICBRAINZ_RECORDING_KEYS: [&str; 2] = ["MUSICBRAINZ_TRACKID", "MusicBrainz Track Id"];
+const MUSICBRAINZ_RELEASE_KEYS: [&str; 2] = ["MUSICBRAINZ_ALBUMID", "MusicBrainz Album Id"];
+const MUSICBRAINZ_UFID_OWNER: &[u8] = b"http://musicbrainz.org\0";
+
+pub fn get_title(tag: &Tag) -> Option<String> {
+    tag.get_string(&ItemKey::TrackTitle)
+        .map(|title| title.to_string())
 }
 
 pub fn get_track_artist(tag: &Tag) -> String {
-    tag.get_string(&ItemKey::TrackArtist)
-        .unwrap_or(UNKNOWN_ARTIST)
-        .to_string()
+    let artists: Vec<&str> = tag.get_texts(&ItemKey::TrackArtist).collect();
+    match artists.is_empty() {
+        true => UNKNOWN_ARTIST.to_string(),
+        false => artists.join("; "),
+    }
 }
 
 pub fn get_album_title(tag: &Tag) -> String {
@@ -31,14 +46,19 @@ pub fn get_album_artist(tag: &Tag) -> String {
         .to_string()
 }
 
+// YEAR, or the year of the recording date (TDRC, DATE, ©day) which is usually "YYYY-MM-DD"
 pub fn get_year(tag: &Tag) -> Option<String> {
     match tag.get_string(&ItemKey::Year) {
         Some(year) => Some(year.to_string()),
-        None => None,
+        None => tag
+            .get_string(&ItemKey::RecordingDate)
+            .and_then(|date| date.split('-').next())
+            .map(|year| year.trim().to_string())
+            .filter(|year| !year.is_empty()),
     }
 }
 
-//TODO: use this in Song
+// unsynchronized lyrics (USLT, LYRICS, ©lyr), loaded on demand rather than stored in Song
 pub fn get_lyrics(tag: &Tag) -> Option<String> {
     match tag.get_string(&ItemKey::Lyrics) {
         Some(lyrics) => Some(lyrics.to_string()),
@@ -53,16 +73,229 @@ pub fn get_track_number(tag: &Tag) -> Option<String> {
     }
 }
 
-pub fn get_genre(tag: &Tag) -> Option<String> {
-    match tag.get_string(&ItemKey::Genre) {
-        Some(genre) => Some(genre.to_string()),
-        None => None,
+// Every artist credited on the track. Values come from repeated fields (Vorbis comments),
+// null separated values (APE, ID3v2.4) and are further split on `separators`.
+pub fn get_track_artists(tag: &Tag, separators: &[String]) -> Vec<String> {
+    split_values(tag.get_texts(&ItemKey::TrackArtist), separators)
+}
+
+pub fn get_genres(tag: &Tag, separators: &[String]) -> Vec<String> {
+    split_values(tag.get_texts(&ItemKey::Genre), separators)
+}
+
+pub fn split_values<'a>(
+    values: impl IntoIterator<Item = &'a str>,
+    separators: &[String],
+) -> Vec<String> {
+    let mut split: Vec<String> = Vec::new();
+    for value in values {
+        let mut parts = vec![value.to_string()];
+        for separator in separators.iter().map(|s| s.as_str()).chain(["\0"]) {
+            parts = parts
+                .iter()
+                .flat_map(|part| part.split(separator))
+                .map(|part| part.to_string())
+                .collect();
+        }
+        for part in parts {
+            let part = part.trim();
+            if !part.is_empty() && !split.iter().any(|s| s == part) {
+                split.push(part.to_string());
+            }
+        }
     }
+    split
 }
 
-pub fn get_total_dur_sec(tag: &Tag) -> u64 {
+// TLEN/LENGTH tag in milliseconds, often missing or wrong so only used when the length can't
+// be computed from the audio stream
+pub fn get_length_ms(tag: &Tag) -> u64 {
     match tag.get_string(&ItemKey::Length) {
-        Some(ms_str) => Duration::from_millis(ms_str.parse().unwrap_or_default()).as_secs(),
+        Some(ms_str) => ms_str.trim().parse().unwrap_or_default(),
         None => 0,
     }
 }
+
+pub fn get_track_total(tag: &Tag) -> Option<u32> {
+    match tag.get_string(&ItemKey::TrackTotal) {
+        Some(total) => parse_number(total),
+        // some taggers store the total in the track number as "3/12"
+        None => tag
+            .get_string(&ItemKey::TrackNumber)
+            .and_then(|track| track.split_once('/'))
+            .and_then(|(_, total)| parse_number(total)),
+    }
+}
+
+pub fn get_disc_number(tag: &Tag) -> Option<u32> {
+    tag.get_string(&ItemKey::DiscNumber).and_then(parse_number)
+}
+
+pub fn get_disc_total(tag: &Tag) -> Option<u32> {
+    match tag.get_string(&ItemKey::DiscTotal) {
+        Some(total) => parse_number(total),
+        None => tag
+            .get_string(&ItemKey::DiscNumber)
+            .and_then(|disc| disc.split_once('/'))
+            .and_then(|(_, total)| parse_number(total)),
+    }
+}
+
+pub fn get_composer(tag: &Tag) -> Option<String> {
+    tag.get_string(&ItemKey::Composer).map(|c| c.to_string())
+}
+
+pub fn get_conductor(tag: &Tag) -> Option<String> {
+    tag.get_string(&ItemKey::Conductor).map(|c| c.to_string())
+}
+
+pub fn get_compilation(tag: &Tag) -> bool {
+    match tag.get_string(&ItemKey::FlagCompilation) {
+        Some(flag) => matches!(flag.trim(), "1" | "true" | "TRUE" | "True"),
+        None => false,
+    }
+}
+
+pub fn get_title_sort(tag: &Tag) -> Option<String> {
+    tag.get_string(&ItemKey::TrackTitleSortOrder)
+        .map(|s| s.to_string())
+}
+
+pub fn get_artist_sort(tag: &Tag) -> Option<String> {
+    tag.get_string(&ItemKey::TrackArtistSortOrder)
+        .map(|s| s.to_string())
+}
+
+pub fn get_album_sort(tag: &Tag) -> Option<String> {
+    tag.get_string(&ItemKey::AlbumTitleSortOrder)
+        .map(|s| s.to_string())
+}
+
+pub fn get_album_artist_sort(tag: &Tag) -> Option<String> {
+    tag.get_string(&ItemKey::AlbumArtistSortOrder)
+        .map(|s| s.to_string())
+}
+
+pub fn get_musicbrainz_recording_id(tag: &Tag) -> Option<String> {
+    get_unknown(tag, &MUSICBRAINZ_RECORDING_KEYS)
+}
+
+pub fn get_musicbrainz_release_id(tag: &Tag) -> Option<String> {
+    get_unknown(tag, &MUSICBRAINZ_RELEASE_KEYS)
+}
+
+// A file read for import. For MP3s the parts of the ID3v2 tag the generic tags lose are kept:
+// the raw tag and the values of multi-valued ID3v2.4 text frames (see `id3`).
+pub struct TagFile {
+    pub file: TaggedFile,
+    pub id3v2: Option<Id3v2Tag>,
+    pub v24_text_frames: HashMap<String, Vec<String>>,
+}
+
+// Reads the tags and properties of the file at `path`, whose type is told by its extension.
+// The file is opened and its tags parsed only once.
+pub fn read_file(path: &str) -> Result<TagFile, LoftyError> {
+    let probe = Probe::open(path)?;
+    if probe.file_type() != Some(FileType::MP3) {
+        return Ok(TagFile {
+            file: probe.read(true)?,
+            id3v2: None,
+            v24_text_frames: HashMap::new(),
+        });
+    }
+    let mut reader = probe.into_inner();
+    let v24_text_frames = id3::read_v24_text_frames_from(&mut reader, &["TPE1", "TCON"]);
+    reader.seek(SeekFrom::Start(0))?;
+    let mp3 = Mp3File::read_from(&mut reader, true)?;
+    Ok(TagFile {
+        id3v2: mp3.id3v2_tag().cloned(),
+        file: mp3.into(),
+        v24_text_frames,
+    })
+}
+
+// ID3v2 keeps MusicBrainz IDs in UFID and TXXX frames whose owner/description is lost when
+// converted to a generic Tag. Returns the (recording, release) IDs.
+pub fn id3v2_musicbrainz_ids(tag: &Id3v2Tag) -> (Option<String>, Option<String>) {
+    (ufid_recording_id(tag), txxx(tag, "MusicBrainz Album Id"))
+}
+
+// Lines of the first SYLT frame holding lyrics, timestamps in MPEG frames are converted using
+// the sample rate
+pub fn read_id3v2_synced_lyrics(
+    path: &str,
+    sample_rate: Option<u32>,
+) -> Option<Vec<(Duration, String)>> {
+    let file = match File::open(path).map(|mut f| Mp3File::read_from(&mut f, false)) {
+        Ok(Ok(file)) => file,
+        _ => return None,
+    };
+    let sylt = file
+        .id3v2_tag()?
+        .iter()
+        .filter(|frame| frame.id_str() == "SYLT")
+        .filter_map(|frame| match frame.content() {
+            FrameValue::Binary(data) => SynchronizedText::parse(data).ok(),
+            _ => None,
+        })
+        .find(|sylt| sylt.information.content_type == SyncTextContentType::Lyrics)?;
+
+    // an MPEG audio frame holds 1152 samples
+    let frame_ms = 1_152_000.0 / sample_rate.unwrap_or(44100) as f64;
+    let lines = sylt
+        .content
+        .into_iter()
+        .map(|(timestamp, text)| {
+            let ms = match sylt.information.timestamp_format {
+                TimestampFormat::MS => timestamp as u64,
+                TimestampFormat::MPEG => (timestamp as f64 * frame_ms) as u64,
+            };
+            // lines often start with a newline since SYLT stores them as one continuous text
+            (Duration::from_millis(ms), text.trim().to_string())
+        })
+        .collect();
+    Some(lines)
+}
+
+fn ufid_recording_id(tag: &Id3v2Tag) -> Option<String> {
+    tag.iter()
+        .filter(|frame| frame.id_str() == "UFID")
+        .find_map(|frame| match frame.content() {
+            FrameValue::Binary(data) if data.starts_with(MUSICBRAINZ_UFID_OWNER) => {
+                String::from_utf8(data[MUSICBRAINZ_UFID_OWNER.len()..].to_vec()).ok()
+            }
+            _ => None,
+        })
+}
+
+fn txxx(tag: &Id3v2Tag, description: &str) -> Option<String> {
+    tag.iter().find_map(|frame| match frame.content() {
+        FrameValue::UserText(text) if text.description.eq_ignore_ascii_case(description) => {
+            Some(text.content.to_owned())
+        }
+        _ => None,
+    })
+}
+
+// Look up a value stored under a key lofty doesn't know about. Matches keys ending with one
+// of `keys` so MP4 freeform atoms ("----:com.apple.iTunes:MusicBrainz Track Id") are found.
+fn get_unknown(tag: &Tag, keys: &[&str]) -> Option<String> {
+    tag.items()
+        .iter()
+        .find_map(|item| match (item.key(), item.value()) {
+            (ItemKey::Unknown(key), ItemValue::Text(value)) => {
+                let key = key.to_lowercase();
+                if keys.iter().any(|k| key.ends_with(&k.to_lowercase())) {
+                    Some(value.to_owned())
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+}
+
+// parse the leading number of values like "3", "03" or "3/12"
+fn parse_number(value: &str) -> Option<u32> {
+    value.split('/').next().and_then(|n| n.trim().parse().ok())
+}
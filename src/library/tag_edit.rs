@@ -0,0 +1,341 @@
+use lofty::id3::v2::{EncodedTextFrame, Frame, FrameFlags, FrameValue, Id3v2Tag, TextEncoding};
+use lofty::mp3::Mp3File;
+use lofty::{
+    read_from_path, AudioFile, FileType, ItemKey, ItemValue, LoftyError, Tag, TagItem, TagType,
+};
+use std::fs::File;
+
+use super::errors::TagWriteError;
+use super::id3;
+use super::song::Song;
+
+// POPM frames are told apart by an email, each player writes its own
+const POPM_EMAIL: &str = "splay";
+// POPM rating of 1 to 5 stars, as most players read them
+const POPM_RATINGS: [u8; 6] = [0, 1, 64, 128, 196, 255];
+const FMPS_RATING: &str = "FMPS_Rating";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TagField {
+    Title,
+    Artists,
+    Album,
+    AlbumArtist,
+    Year,
+    Genres,
+    TrackNumber,
+    DiscNumber,
+}
+
+impl TagField {
+    pub const ALL: [TagField; 8] = [
+        TagField::Title,
+        TagField::Artists,
+        TagField::Album,
+        TagField::AlbumArtist,
+        TagField::Year,
+        TagField::Genres,
+        TagField::TrackNumber,
+        TagField::DiscNumber,
+    ];
+    // fields that make sense to set on every track of an album at once
+    pub const ALBUM: [TagField; 6] = [
+        TagField::Album,
+        TagField::AlbumArtist,
+        TagField::Artists,
+        TagField::Year,
+        TagField::Genres,
+        TagField::DiscNumber,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TagField::Title => "Title",
+            TagField::Artists => "Artists",
+            TagField::Album => "Album",
+            TagField::AlbumArtist => "Album artist",
+            TagField::Year => "Year",
+            TagField::Genres => "Genres",
+            TagField::TrackNumber => "Track",
+            TagField::DiscNumber => "Disc",
+        }
+    }
+
+    // current value as shown in the edit form, multiple values are separated by "; "
+    pub fn value(&self, song: &Song) -> String {
+        match self {
+            TagField::Title => song.title.to_owned(),
+            TagField::Artists => song.artists.join("; "),
+            TagField::Album => song.album_title.to_owned(),
+            TagField::AlbumArtist => song.album_artist.to_owned(),
+            TagField::Year => song.year.clone().unwrap_or_default(),
+            TagField::Genres => song.genres.join("; "),
+            TagField::TrackNumber => song.track_number.clone().unwrap_or_default(),
+            TagField::DiscNumber => match (song.disc_number, song.disc_total) {
+                (Some(disc), Some(total)) => format!("{}/{}", disc, total),
+                (Some(disc), None) => disc.to_string(),
+                _ => String::new(),
+            },
+        }
+    }
+
+    fn id3v2_frame(&self) -> &'static str {
+        match self {
+            TagField::Title => "TIT2",
+            TagField::Artists => "TPE1",
+            TagField::Album => "TALB",
+            TagField::AlbumArtist => "TPE2",
+            TagField::Year => "TDRC",
+            TagField::Genres => "TCON",
+            TagField::TrackNumber => "TRCK",
+            TagField::DiscNumber => "TPOS",
+        }
+    }
+
+    // keys the field is stored in, the first one the tag type has is written
+    fn item_keys(&self) -> Vec<ItemKey> {
+        match self {
+            TagField::Title => vec![ItemKey::TrackTitle],
+            TagField::Artists => vec![ItemKey::TrackArtist],
+            TagField::Album => vec![ItemKey::AlbumTitle],
+            TagField::AlbumArtist => vec![ItemKey::AlbumArtist],
+            // APE only has a year, Vorbis comments have both DATE and YEAR
+            TagField::Year => vec![ItemKey::RecordingDate, ItemKey::Year],
+            TagField::Genres => vec![ItemKey::Genre],
+            TagField::TrackNumber => vec![ItemKey::TrackNumber],
+            TagField::DiscNumber => vec![ItemKey::DiscNumber],
+        }
+    }
+
+    fn is_multi_valued(&self) -> bool {
+        matches!(self, TagField::Artists | TagField::Genres)
+    }
+}
+
+// Write `changes` to the file at `path`, an empty value removes the field.
+// MP3s are edited through their ID3v2 frames directly so frames the generic tag can't
+// represent (MusicBrainz IDs, synced lyrics, ...) are kept.
+pub fn write(path: &str, changes: &[(TagField, String)]) -> Result<(), TagWriteError> {
+    let to_error = |source: LoftyError| TagWriteError::Write {
+        path: path.to_string(),
+        source,
+    };
+    let mut file = read_from_path(path, false).map_err(to_error)?;
+    let has_id3v2 = file.tag(&TagType::Id3v2).is_some();
+    if *file.file_type() == FileType::MP3 && has_id3v2 {
+        return write_id3v2(path, changes).map_err(to_error);
+    }
+
+    if file.primary_tag().is_none() {
+        file.insert_tag(Tag::new(file.primary_tag_type()));
+    }
+    let tag = match file.primary_tag_mut() {
+        Some(tag) => tag,
+        None => return Ok(()),
+    };
+    if let Err(field) = set_items(tag, changes) {
+        return Err(TagWriteError::Unsupported {
+            path: path.to_string(),
+            field: field.label(),
+        });
+    }
+    tag.save_to_path(path).map_err(to_error)
+}
+
+// Sets `changes` on a generic tag, returns the first field its tag type can't store
+fn set_items(tag: &mut Tag, changes: &[(TagField, String)]) -> Result<(), TagField> {
+    for (field, value) in changes {
+        let keys = field.item_keys();
+        for key in &keys {
+            tag.remove_key(key);
+        }
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        if !keys
+            .into_iter()
+            .any(|key| tag.insert_text(key, value.to_string()))
+        {
+            return Err(*field);
+        }
+    }
+    Ok(())
+}
+
+fn write_id3v2(path: &str, changes: &[(TagField, String)]) -> Result<(), LoftyError> {
+    let mut file = Mp3File::read_from(&mut File::open(path)?, false)?;
+    let tag = match file.id3v2_tag_mut() {
+        Some(tag) => tag,
+        None => return Ok(()),
+    };
+    restore_multi_valued(path, tag)?;
+    for (field, value) in changes {
+        let id = field.id3v2_frame();
+        tag.remove(id);
+        if value.trim().is_empty() {
+            continue;
+        }
+        // ID3v2.4 separates multiple values with null bytes
+        let value = match field.is_multi_valued() {
+            true => split(value).join("\0"),
+            false => value.trim().to_string(),
+        };
+        let frame_value = FrameValue::Text {
+            encoding: TextEncoding::UTF8,
+            value,
+        };
+        tag.insert(Frame::new(id, frame_value, FrameFlags::default())?);
+    }
+    tag.write_to_path(path)
+}
+
+// Write `rating` (1 to 5 stars, 0 removes it) to the file at `path`: MP3s get a POPM frame and
+// a FMPS_Rating TXXX frame, other files a FMPS_RATING field. Ratings set by other players are
+// kept.
+pub fn write_rating(path: &str, rating: u8) -> Result<(), TagWriteError> {
+    let to_error = |source: LoftyError| TagWriteError::Write {
+        path: path.to_string(),
+        source,
+    };
+    let mut file = read_from_path(path, false).map_err(to_error)?;
+    let has_id3v2 = file.tag(&TagType::Id3v2).is_some();
+    if *file.file_type() == FileType::MP3 && has_id3v2 {
+        return write_id3v2_rating(path, rating).map_err(to_error);
+    }
+
+    if file.primary_tag().is_none() {
+        file.insert_tag(Tag::new(file.primary_tag_type()));
+    }
+    let tag = match file.primary_tag_mut() {
+        Some(tag) => tag,
+        None => return Ok(()),
+    };
+    let key = ItemKey::Unknown(FMPS_RATING.to_uppercase());
+    tag.remove_key(&key);
+    if rating > 0 {
+        // lofty doesn't know the key, formats that don't allow custom fields leave it out
+        tag.insert_item_unchecked(TagItem::new(key, ItemValue::Text(fmps_rating(rating))));
+    }
+    tag.save_to_path(path).map_err(to_error)
+}
+
+fn write_id3v2_rating(path: &str, rating: u8) -> Result<(), LoftyError> {
+    let mut file = Mp3File::read_from(&mut File::open(path)?, false)?;
+    let tag = match file.id3v2_tag_mut() {
+        Some(tag) => tag,
+        None => return Ok(()),
+    };
+    restore_multi_valued(path, tag)?;
+    // frames can only be removed by id, put back the ones that aren't our rating
+    let kept: Vec<Frame> = tag
+        .iter()
+        .filter(|frame| matches!(frame.id_str(), "POPM" | "TXXX") && !is_own_rating(frame))
+        .cloned()
+        .collect();
+    tag.remove("POPM");
+    tag.remove("TXXX");
+    for frame in kept {
+        tag.insert(frame);
+    }
+    if rating > 0 {
+        // email, rating from 1 to 255 and no play counter
+        let mut popm = format!("{}\0", POPM_EMAIL).into_bytes();
+        popm.push(POPM_RATINGS[rating.min(5) as usize]);
+        tag.insert(Frame::new(
+            "POPM",
+            FrameValue::Binary(popm),
+            FrameFlags::default(),
+        )?);
+        let fmps = FrameValue::UserText(EncodedTextFrame {
+            encoding: TextEncoding::UTF8,
+            description: FMPS_RATING.to_string(),
+            content: fmps_rating(rating),
+        });
+        tag.insert(Frame::new("TXXX", fmps, FrameFlags::default())?);
+    }
+    tag.write_to_path(path)
+}
+
+// lofty only keeps the first value of multi-valued frames, put the others back so they aren't
+// lost when the tag is written
+fn restore_multi_valued(path: &str, tag: &mut Id3v2Tag) -> Result<(), LoftyError> {
+    let multi_valued = id3::read_v24_text_frames(path, &["TPE1", "TCON"]);
+    for (id, values) in multi_valued.iter().filter(|(_, values)| values.len() > 1) {
+        let frame_value = FrameValue::Text {
+            encoding: TextEncoding::UTF8,
+            value: values.join("\0"),
+        };
+        tag.insert(Frame::new(id, frame_value, FrameFlags::default())?);
+    }
+    Ok(())
+}
+
+fn is_own_rating(frame: &Frame) -> bool {
+    match frame.content() {
+        FrameValue::Binary(data) => data.starts_with(format!("{}\0", POPM_EMAIL).as_bytes()),
+        FrameValue::UserText(text) => text.description.eq_ignore_ascii_case(FMPS_RATING),
+        _ => false,
+    }
+}
+
+// FMPS ratings go from 0.0 to 1.0
+fn fmps_rating(rating: u8) -> String {
+    (rating.min(5) as f32 / 5.0).to_string()
+}
+
+// values of a multi-valued field as entered in the edit form
+fn split(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::tag;
+
+    fn set(tag_type: TagType, changes: &[(TagField, String)]) -> (Tag, Result<(), TagField>) {
+        let mut tag = Tag::new(tag_type);
+        let result = set_items(&mut tag, changes);
+        (tag, result)
+    }
+
+    #[test]
+    fn year_replaces_both_keys() {
+        let mut vorbis = Tag::new(TagType::VorbisComments);
+        vorbis.insert_text(ItemKey::Year, "1999".to_string());
+        vorbis.insert_text(ItemKey::RecordingDate, "1999-04-01".to_string());
+        set_items(&mut vorbis, &[(TagField::Year, " 2001 ".to_string())]).unwrap();
+        assert_eq!(vorbis.get_string(&ItemKey::Year), None);
+        assert_eq!(vorbis.get_string(&ItemKey::RecordingDate), Some("2001"));
+        assert_eq!(tag::get_year(&vorbis).as_deref(), Some("2001"));
+
+        set_items(&mut vorbis, &[(TagField::Year, String::new())]).unwrap();
+        assert_eq!(tag::get_year(&vorbis), None);
+    }
+
+    #[test]
+    fn year_is_written_to_the_key_the_tag_type_has() {
+        let (ape, result) = set(TagType::Ape, &[(TagField::Year, "2001".to_string())]);
+        assert_eq!(result, Ok(()));
+        assert_eq!(ape.get_string(&ItemKey::Year), Some("2001"));
+        assert_eq!(tag::get_year(&ape).as_deref(), Some("2001"));
+    }
+
+    #[test]
+    fn fields_the_tag_type_lacks_are_errors() {
+        let changes = [
+            (TagField::Title, "Title".to_string()),
+            (TagField::AlbumArtist, "Someone".to_string()),
+        ];
+        let (_, result) = set(TagType::RiffInfo, &changes);
+        assert_eq!(result, Err(TagField::AlbumArtist));
+        // removing it is fine
+        let (_, result) = set(TagType::RiffInfo, &[(TagField::AlbumArtist, String::new())]);
+        assert_eq!(result, Ok(()));
+    }
+}
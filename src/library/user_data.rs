@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+
+use super::song::Song;
+
+// What the user added to a song that isn't read from its file. It's saved by path next to the
+// library db, in a format that doesn't change with `Song`, so it survives the songs being
+// imported again.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserData {
+    pub play_count: u32,
+    pub last_played: Option<u64>,
+    pub added: u64,
+    pub rating: u8,
+    pub loved: bool,
+}
+
+impl UserData {
+    pub fn of(song: &Song) -> UserData {
+        UserData {
+            play_count: song.play_count,
+            last_played: song.last_played,
+            added: song.added,
+            rating: song.rating,
+            loved: song.loved,
+        }
+    }
+
+    pub fn apply(&self, song: &mut Song) {
+        song.play_count = self.play_count;
+        song.last_played = self.last_played;
+        song.added = self.added;
+        // at most 5 stars, the file could have been edited by hand
+        song.rating = self.rating.min(5);
+        song.loved = self.loved;
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct UserDataFile {
+    #[serde(default)]
+    songs: BTreeMap<String, UserData>,
+}
+
+pub fn load(path: &str) -> Result<BTreeMap<String, UserData>, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    Ok(toml::from_str::<UserDataFile>(&text)?.songs)
+}
+
+pub fn save(path: &str, songs: BTreeMap<String, UserData>) -> Result<(), Box<dyn Error>> {
+    fs::write(path, toml::to_string(&UserDataFile { songs })?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_data_loads_again() {
+        let path = std::env::temp_dir()
+            .join(format!("splay-user-data-{}.toml", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let mut song = Song::new("Title".to_string(), "/music/a \"b\".mp3".to_string());
+        song.play_count = 3;
+        song.last_played = Some(1_700_000_000);
+        song.added = 1_600_000_000;
+        song.rating = 4;
+        song.loved = true;
+        let unrated = Song::new("Other".to_string(), "/music/other.mp3".to_string());
+
+        let songs = BTreeMap::from([
+            (song.path.to_owned(), UserData::of(&song)),
+            (unrated.path.to_owned(), UserData::of(&unrated)),
+        ]);
+        save(&path, songs.clone()).unwrap();
+        let loaded = load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(loaded, songs);
+
+        let mut read_again = Song::new("Title".to_string(), song.path.to_owned());
+        loaded[&song.path].apply(&mut read_again);
+        assert_eq!(UserData::of(&read_again), UserData::of(&song));
+    }
+
+    #[test]
+    fn ratings_are_at_most_five_stars() {
+        let mut song = Song::new("Title".to_string(), "/music/a.mp3".to_string());
+        let data = UserData {
+            rating: 9,
+            ..Default::default()
+        };
+        data.apply(&mut song);
+        assert_eq!(song.rating, 5);
+    }
+}
@@ -1,15 +1,24 @@
+mod config;
 mod input;
 mod library;
 mod player;
 mod queue;
+mod scrobble;
 mod state;
 mod ui;
 mod utils;
 
+use crate::config::SplayConfig;
+use crate::library::history::History;
+use crate::library::path_pattern::PathPattern;
+use crate::library::playlist::Playlists;
+use crate::library::query::Query;
 use crate::library::Library;
 use crate::player::rodio_player::RodioPlayer;
 use crate::player::symphonia_player::SymphoniaPlayer;
 use crate::player::Player;
+use crate::queue::session::Session;
+use crate::scrobble::Scrobbler;
 use crate::state::AppState;
 use crate::utils::constants::requests::*;
 
@@ -20,21 +29,11 @@ use std::fs::File;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 use std::io::Read;
-use serde::Deserialize;
 
 use toml::Table;
 
-#[derive(Deserialize)]
-struct SplayConfig {
-    media: Media
-}
-
-#[derive(Deserialize)]
-struct Media {
-    directories: Vec<String>
-}
-
 
 fn main() {
     let _ = WriteLogger::init(
@@ -54,27 +53,45 @@ fn main() {
 
 
     // TODO: change to other path on system in XDG_CONFIG_HOME 
-    match File::open("config.toml") {
+    let config: SplayConfig = match File::open("config.toml") {
         Ok(mut in_file) => {
             let mut in_contents = String::new();
             in_file.read_to_string(&mut in_contents).unwrap();
-            let config: SplayConfig = toml::from_str(in_contents.as_str()).unwrap();
-            
-            for dir in config.media.directories {
-                // TODO: allow to use ~
-                match lib.import_dir(dir.as_str()) {
-                    Ok(_) => {
-                        let _ = lib.save_to_file("db".to_string());
-                    }
-                    Err(e) => error!("{}", e),
-                }
-            }
+            toml::from_str(in_contents.as_str()).unwrap()
+        }
+        Err(_) => SplayConfig::default(),
+    };
+
+    let mut path_patterns = Vec::new();
+    for pattern in config.library.path_patterns.iter() {
+        match pattern.parse::<PathPattern>() {
+            Ok(pattern) => path_patterns.push(pattern),
+            Err(e) => error!("{}", e),
         }
-        Err(_) => (),
     }
+    lib.path_patterns = path_patterns;
+    lib.tag_separators = config.library.tag_separators.clone();
+    lib.write_ratings = config.library.write_ratings;
 
-
+    if let Err(err) = lib.load_from_file("db".to_string()) {
+        info!("No library database loaded: {}", err);
+    }
+    // TODO: allow to use ~
+    let import_report = lib.rescan(&config.media.directories);
+    let _ = lib.save_to_file("db".to_string());
+
+    let mut playlists = Playlists::load();
+    for smart in config.smart_playlists.iter() {
+        if let Err(e) = playlists.add_from_config(&smart.name, &smart.rules) {
+            error!("Smart playlist \"{}\": {}", smart.name, e);
+        }
+    }
+    playlists.update_smart(&lib);
     state.lock().unwrap().library = lib;
+    state.lock().unwrap().playlists = playlists;
+    state.lock().unwrap().history = History::load();
+    state.lock().unwrap().scrobbler = Scrobbler::start(&config.scrobbling);
+    state.lock().unwrap().import_report = import_report;
 
     let mut join_handlers = vec![];
 
@@ -83,10 +100,40 @@ fn main() {
     let (player_tx, player_rx): (Sender<PlayerRequests>, Receiver<PlayerRequests>) =
         mpsc::channel();
 
+    // pick up the queue and song of the last session, paused where it stopped
+    if let Some(session) = Session::load() {
+        if let Some(progress) = session.restore(&mut state.lock().unwrap()) {
+            let _ = player_tx.send(PlayerRequests::Restore(progress));
+        }
+    }
+    // save the session every so often in case splay doesn't quit cleanly
+    let cloned_state = state.clone();
+    thread::spawn(move || {
+        let mut saved = Session::default();
+        loop {
+            thread::sleep(Duration::from_secs(30));
+            let session = Session::new(&cloned_state.lock().unwrap());
+            if session != saved {
+                if let Err(err) = session.save() {
+                    error!("Could not save the session: {}", err);
+                }
+                saved = session;
+            }
+        }
+    });
+
     let cloned_state = state.clone();
     let cloned_main_tx = main_tx.clone();
+    let art_mode = config.ui.album_art;
+    let mut saved_searches = Vec::new();
+    for search in config.searches.iter() {
+        match search.query.parse::<Query>() {
+            Ok(query) => saved_searches.push((search.name.clone(), query)),
+            Err(e) => error!("Saved search \"{}\": {}", search.name, e),
+        }
+    }
     join_handlers.push(thread::spawn(move || {
-        ui::start(cloned_state, ui_rx, cloned_main_tx)
+        ui::start(cloned_state, ui_rx, cloned_main_tx, art_mode, saved_searches)
     }));
 
     let cloned_state = state.clone();
@@ -96,9 +143,9 @@ fn main() {
     }));
 
     let cloned_state = state.clone();
-    // let cloned_main_tx = main_tx.clone();
+    let cloned_main_tx = main_tx.clone();
     join_handlers.push(thread::spawn(move || {
-        player.listen(cloned_state, player_rx)
+        player.listen(cloned_state, player_rx, cloned_main_tx)
     }));
 
     loop {
@@ -111,12 +158,19 @@ fn main() {
             }
             Ok(request) => match request {
                 AppRequests::Quit => {
+                    if let Err(err) = Session::new(&state.lock().unwrap()).save() {
+                        error!("Could not save the session: {}", err);
+                    }
+                    // the next session resumes the current song, it's counted once it ends
+                    state.lock().unwrap().player.listened = Duration::ZERO;
                     let _ = ui_tx.send(UIRequests::Quit);
                     let _ = player_tx.send(PlayerRequests::Stop);
                     let _ = player_tx.send(PlayerRequests::Quit);
                     for handler in join_handlers {
                         let _ = handler.join();
                     }
+                    // keep the play counts
+                    let _ = state.lock().unwrap().library.save_to_file("db".to_string());
                     info!("Gracefully shutting down");
                     std::process::exit(0);
                 }
@@ -126,6 +180,19 @@ fn main() {
                 AppRequests::PlayerRequests(request) => {
                     let _ = player_tx.send(request);
                 }
+                AppRequests::Rescan => {
+                    let cloned_state = state.clone();
+                    let directories = config.media.directories.clone();
+                    thread::spawn(move || {
+                        // scan a copy so the ui and player aren't blocked while files are read
+                        let mut library = cloned_state.lock().unwrap().library.clone();
+                        let report = library.rescan(&directories);
+                        let _ = library.save_to_file("db".to_string());
+                        let mut guard = cloned_state.lock().unwrap();
+                        guard.library.replace(library);
+                        guard.import_report = report;
+                    });
+                }
             },
         }
     }
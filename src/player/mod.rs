@@ -1,9 +1,101 @@
 pub mod output;
 pub mod rodio_player;
 pub mod symphonia_player;
-use crate::{state::AppState, utils::constants::requests::*};
-use std::sync::{mpsc::Receiver, Arc, Mutex};
+use crate::{
+    library::{history::Play, radio, song::Song},
+    state::AppState,
+    utils::constants::requests::*,
+};
+use std::collections::HashSet;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
 
 pub trait Player {
-    fn listen(&mut self, app_state: Arc<Mutex<AppState>>, rx: Receiver<PlayerRequests>);
+    // `main_tx` is used to report when a song played to the end
+    fn listen(
+        &mut self,
+        app_state: Arc<Mutex<AppState>>,
+        rx: Receiver<PlayerRequests>,
+        main_tx: Sender<AppRequests>,
+    );
+}
+
+// songs added at a time in radio mode
+const RADIO_SONGS: usize = 10;
+// the last songs played that radio mode won't pick again
+const RADIO_HISTORY: usize = 100;
+
+// Moves the current song to the previous ones and takes the one to play from the queue, which
+// repeats the current song in repeat-one mode unless it's skipped. In radio mode songs similar
+// to the last one are queued when the queue runs out.
+pub fn next_song(state: &mut AppState, skip: bool) -> Option<Song> {
+    finish_song(state, skip);
+    if let Some(song) = state.player.curr_song.take() {
+        state.queue.add_to_previous(song);
+    }
+    let song = match skip {
+        true => state.queue.skip(),
+        false => state.queue.next(),
+    };
+    if song.is_some() || !state.queue.radio() {
+        return song;
+    }
+    let seed = state.queue.recent(1).next()?.clone();
+    let recent: HashSet<&str> = state
+        .queue
+        .recent(RADIO_HISTORY)
+        .map(|song| song.path.as_str())
+        .collect();
+    let similar: Vec<Song> = radio::similar(&state.library, &seed, &recent, RADIO_SONGS)
+        .into_iter()
+        .cloned()
+        .collect();
+    for song in similar {
+        state.queue.add_upcoming(song);
+    }
+    state.queue.skip()
+}
+
+// Drops the current song, which couldn't be played, and takes the next one from the queue. It
+// isn't added to the previous songs, so repeat-all doesn't come back to it, and radio mode
+// doesn't queue more songs, so a queue of files that all fail runs out and stops.
+pub fn skip_failed_song(state: &mut AppState) -> Option<Song> {
+    state.player.curr_song = None;
+    state.player.listened = Default::default();
+    state.queue.skip()
+}
+
+// tell the scrobbling services about the song that started playing
+pub fn song_started(state: &AppState, song: &Song) {
+    if let Some(scrobbler) = &state.scrobbler {
+        scrobbler.now_playing(song);
+    }
+}
+
+// Adds the current song to the history, its play count and the scrobbles if enough of it was
+// listened to. Called when it's replaced or stopped, `skipped` if that happened before its end.
+pub fn finish_song(state: &mut AppState, skipped: bool) {
+    let listened = std::mem::take(&mut state.player.listened);
+    let Some(song) = state.player.curr_song.as_ref() else {
+        return;
+    };
+    let Some(play) = Play::new(song, listened, skipped) else {
+        return;
+    };
+    state.library.record_play(&play.path, play.time);
+    if let Some(scrobbler) = &state.scrobbler {
+        scrobbler.listen(&play);
+    }
+    if let Err(err) = state.history.add(play) {
+        error!("Could not save the play history: {}", err);
+    }
+}
+
+// whether `path` is the song being played, to ignore the end of a song that was replaced
+pub fn is_current(state: &AppState, path: &str) -> bool {
+    state
+        .player
+        .curr_song
+        .as_ref()
+        .is_some_and(|song| song.path == path)
 }
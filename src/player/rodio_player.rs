@@ -1,10 +1,10 @@
-use crate::player::{Player, PlayerRequests};
+use crate::player::{self, Player, PlayerRequests};
 use crate::state::AppState;
-use crate::utils::constants::PlayerStates;
-use rodio::{Decoder, OutputStream, Sink};
+use crate::utils::constants::{requests::AppRequests, PlayerStates};
+use rodio::{Decoder, OutputStream, Sink, Source};
 use std::fs::File;
 use std::io::BufReader;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
@@ -20,7 +20,12 @@ impl RodioPlayer {
 
 impl Player for RodioPlayer {
     // create a player thread with a loop that receives requests for player functions
-    fn listen(&mut self, app_state: Arc<Mutex<AppState>>, rx: Receiver<PlayerRequests>) {
+    fn listen(
+        &mut self,
+        app_state: Arc<Mutex<AppState>>,
+        rx: Receiver<PlayerRequests>,
+        main_tx: Sender<AppRequests>,
+    ) {
         let mut join_handle: Option<JoinHandle<()>> = None;
 
         loop {
@@ -37,26 +42,84 @@ impl Player for RodioPlayer {
                         PlayerRequests::Stop => {
                             app_state.lock().unwrap().player.curr_state = PlayerStates::STOPPED;
                             join_handle.take().map(JoinHandle::join);
-                            app_state.lock().unwrap().player.curr_song = None;
+                            let mut state = app_state.lock().unwrap();
+                            player::finish_song(&mut state, true);
+                            state.player.curr_song = None;
                         }
-                        PlayerRequests::Start => {
+                        // the song was replaced before its end was handled
+                        PlayerRequests::SongEnded(ref path) | PlayerRequests::SongFailed(ref path)
+                            if !player::is_current(&app_state.lock().unwrap(), path) => {}
+                        PlayerRequests::Start
+                        | PlayerRequests::Next
+                        | PlayerRequests::SongEnded(_)
+                        | PlayerRequests::SongFailed(_)
+                        | PlayerRequests::Restore(_) => {
                             // stop player if previously playing
                             app_state.lock().unwrap().player.curr_state = PlayerStates::STOPPED;
                             join_handle.take().map(JoinHandle::join);
                             app_state.lock().unwrap().player.curr_state = PlayerStates::PLAYING;
 
-                            // fetch which song is selected in the UI
-                            // TODO: maybe just have other threads modify player.curr_song instead
-                            let song = match app_state.lock().unwrap().ui.selected_song.to_owned() {
-                                Some(song) => song,
-                                None => continue,
+                            let song = match request {
+                                PlayerRequests::Next
+                                | PlayerRequests::SongEnded(_)
+                                | PlayerRequests::SongFailed(_) => {
+                                    let mut state = app_state.lock().unwrap();
+                                    let next = match request {
+                                        PlayerRequests::Next => player::next_song(&mut state, true),
+                                        PlayerRequests::SongEnded(_) => {
+                                            player::next_song(&mut state, false)
+                                        }
+                                        // a song that failed is dropped from the queue
+                                        _ => player::skip_failed_song(&mut state),
+                                    };
+                                    match next {
+                                        Some(song) => song,
+                                        None => {
+                                            state.player.curr_state = PlayerStates::STOPPED;
+                                            continue;
+                                        }
+                                    }
+                                }
+                                PlayerRequests::Restore(_) => {
+                                    match app_state.lock().unwrap().player.curr_song.to_owned() {
+                                        Some(song) => song,
+                                        None => continue,
+                                    }
+                                }
+                                // fetch which song is selected in the UI
+                                // TODO: maybe just have other threads modify player.curr_song
+                                // instead
+                                _ => {
+                                    let mut state = app_state.lock().unwrap();
+                                    player::finish_song(&mut state, true);
+                                    match state.ui.selected_song.to_owned() {
+                                        Some(song) => song,
+                                        None => continue,
+                                    }
+                                }
                             };
 
-                            app_state.lock().unwrap().player.curr_song = Some(song.to_owned());
+                            let mut state = app_state.lock().unwrap();
+                            state.player.curr_song = Some(song.to_owned());
+                            // a restored song waits paused
+                            if !matches!(request, PlayerRequests::Restore(_)) {
+                                player::song_started(&state, &song);
+                            }
+                            drop(state);
+                            let start = match request {
+                                PlayerRequests::Restore(progress) => {
+                                    app_state.lock().unwrap().player.curr_state =
+                                        PlayerStates::PAUSED;
+                                    progress
+                                }
+                                _ => Duration::ZERO,
+                            };
 
                             let cloned_state = app_state.clone();
-                            join_handle =
-                                Some(thread::spawn(move || player(song.path, cloned_state)));
+                            let cloned_main_tx = main_tx.clone();
+                            join_handle = Some(thread::spawn(move || {
+                                player(song.path, start, cloned_state, cloned_main_tx)
+                            }));
                         }
                         PlayerRequests::PlayPause => {
                             match app_state.lock().unwrap().player.curr_state {
@@ -121,13 +184,28 @@ impl Player for RodioPlayer {
     }
 }
 
-fn player(path: String, app_state: Arc<Mutex<AppState>>) {
+// plays the song at `path` from `start`
+fn player(
+    path: String,
+    start: Duration,
+    app_state: Arc<Mutex<AppState>>,
+    main_tx: Sender<AppRequests>,
+) {
     let tick_rate = 250;
     let (_stream, stream_handle) = OutputStream::try_default().unwrap();
     let sink = Sink::try_new(&stream_handle).unwrap();
-    let file = BufReader::new(File::open(path).unwrap());
-    sink.append(Decoder::new(BufReader::new(file)).unwrap());
-    app_state.lock().unwrap().player.progress = Duration::ZERO;
+    let source = File::open(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|file| Decoder::new(BufReader::new(file)).map_err(|err| err.to_string()));
+    match source {
+        Ok(source) => sink.append(source.skip_duration(start)),
+        Err(err) => {
+            error!("Could not play {}: {}", path, err);
+            let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::SongFailed(path)));
+            return;
+        }
+    }
+    app_state.lock().unwrap().player.progress = start;
     loop {
         match app_state.lock().unwrap().player.curr_state {
             PlayerStates::STOPPED => {
@@ -136,6 +214,7 @@ fn player(path: String, app_state: Arc<Mutex<AppState>>) {
             }
             PlayerStates::PAUSED => {
                 sink.pause();
+                thread::sleep(Duration::from_millis(tick_rate));
                 continue;
             }
             PlayerStates::PLAYING => {
@@ -143,6 +222,8 @@ fn player(path: String, app_state: Arc<Mutex<AppState>>) {
             }
         }
         if sink.empty() {
+            let request = PlayerRequests::SongEnded(path);
+            let _ = main_tx.send(AppRequests::PlayerRequests(request));
             break;
         }
         thread::sleep(Duration::from_millis(tick_rate));
@@ -150,6 +231,7 @@ fn player(path: String, app_state: Arc<Mutex<AppState>>) {
                                                    //of times
                                                    // update player time with how long the last packet took to play
         guard.player.progress = guard.player.progress + Duration::from_millis(tick_rate);
+        guard.player.listened += Duration::from_millis(tick_rate);
         drop(guard);
     }
 }
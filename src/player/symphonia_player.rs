@@ -1,16 +1,18 @@
 use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
-use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
+use symphonia::core::units::Time;
 
-use crate::player::PlayerRequests;
+use crate::player::{self, PlayerRequests};
 use crate::state::AppState;
-use crate::utils::constants::PlayerStates;
+use crate::utils::constants::{requests::AppRequests, PlayerStates};
 use std::fs::File;
 use std::path::Path;
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::RecvError;
+use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
@@ -27,7 +29,12 @@ impl SymphoniaPlayer {
 
 impl Player for SymphoniaPlayer {
     // listen for actions the player should take
-    fn listen(&mut self, app_state: Arc<Mutex<AppState>>, rx: Receiver<PlayerRequests>) {
+    fn listen(
+        &mut self,
+        app_state: Arc<Mutex<AppState>>,
+        rx: Receiver<PlayerRequests>,
+        main_tx: Sender<AppRequests>,
+    ) {
         let mut join_handle: Option<JoinHandle<()>> = None;
 
         let _result = loop {
@@ -37,7 +44,9 @@ impl Player for SymphoniaPlayer {
                     PlayerRequests::Stop => {
                         app_state.lock().unwrap().player.curr_state = PlayerStates::STOPPED;
                         join_handle.take().map(JoinHandle::join);
-                        app_state.lock().unwrap().player.curr_song = None;
+                        let mut state = app_state.lock().unwrap();
+                        player::finish_song(&mut state, true);
+                        state.player.curr_song = None;
                     }
                     PlayerRequests::Pause => {
                         app_state.lock().unwrap().player.curr_state = PlayerStates::PAUSED;
@@ -56,7 +65,14 @@ impl Player for SymphoniaPlayer {
                             _ => (),
                         }
                     }
-                    PlayerRequests::Start => {
+                    // the song was replaced before its end was handled
+                    PlayerRequests::SongEnded(ref path) | PlayerRequests::SongFailed(ref path)
+                        if !player::is_current(&app_state.lock().unwrap(), path) => {}
+                    PlayerRequests::Start
+                    | PlayerRequests::Next
+                    | PlayerRequests::SongEnded(_)
+                    | PlayerRequests::SongFailed(_)
+                    | PlayerRequests::Restore(_) => {
                         // stop player if previously playing
                         app_state.lock().unwrap().player.curr_state = PlayerStates::STOPPED;
                         join_handle.take().map(JoinHandle::join);
@@ -64,14 +80,52 @@ impl Player for SymphoniaPlayer {
 
                         // init setup for playing a song
 
-                        // fetch which song is selected in the UI
-                        // TODO: maybe just have other threads modify player.curr_song instead
-                        let song = match app_state.lock().unwrap().ui.selected_song.to_owned() {
-                            Some(song) => song,
-                            None => continue,
+                        let song = match request {
+                            PlayerRequests::Next
+                            | PlayerRequests::SongEnded(_)
+                            | PlayerRequests::SongFailed(_) => {
+                                let mut state = app_state.lock().unwrap();
+                                let next = match request {
+                                    PlayerRequests::Next => player::next_song(&mut state, true),
+                                    PlayerRequests::SongEnded(_) => {
+                                        player::next_song(&mut state, false)
+                                    }
+                                    // a song that failed is dropped from the queue
+                                    _ => player::skip_failed_song(&mut state),
+                                };
+                                match next {
+                                    Some(song) => song,
+                                    None => {
+                                        state.player.curr_state = PlayerStates::STOPPED;
+                                        continue;
+                                    }
+                                }
+                            }
+                            PlayerRequests::Restore(_) => {
+                                match app_state.lock().unwrap().player.curr_song.to_owned() {
+                                    Some(song) => song,
+                                    None => continue,
+                                }
+                            }
+                            // fetch which song is selected in the UI
+                            // TODO: maybe just have other threads modify player.curr_song instead
+                            _ => {
+                                let mut state = app_state.lock().unwrap();
+                                player::finish_song(&mut state, true);
+                                match state.ui.selected_song.to_owned() {
+                                    Some(song) => song,
+                                    None => continue,
+                                }
+                            }
                         };
 
-                        app_state.lock().unwrap().player.curr_song = Some(song.to_owned());
+                        let mut state = app_state.lock().unwrap();
+                        state.player.curr_song = Some(song.to_owned());
+                        // a restored song waits paused
+                        if !matches!(request, PlayerRequests::Restore(_)) {
+                            player::song_started(&state, &song);
+                        }
+                        drop(state);
 
                         let song_path = Path::new(&song.path);
                         let mut hint = Hint::new();
@@ -81,11 +135,18 @@ impl Player for SymphoniaPlayer {
                             }
                         }
 
+                        // a song that can't be played is skipped
+                        let failed = |err: String| {
+                            error!("Could not play {}: {}", song.path, err);
+                            let request = PlayerRequests::SongFailed(song.path.to_owned());
+                            let _ = main_tx.send(AppRequests::PlayerRequests(request));
+                        };
+
                         let source = match File::open(song_path) {
                             Ok(f) => Box::new(f),
                             Err(err) => {
-                                panic!("Could not open song at path {}. Reason: {}", song.path, err)
-                                //TODO: return Result instead of panic here
+                                failed(err.to_string());
+                                continue;
                             }
                         };
 
@@ -99,18 +160,33 @@ impl Player for SymphoniaPlayer {
 
                         let metadata_opts: MetadataOptions = Default::default();
 
-                        let probed = symphonia::default::get_probe()
-                            .format(&hint, media_source_stream, &format_opts, &metadata_opts)
-                            .expect("unsupported media format");
+                        let probed = match symphonia::default::get_probe().format(
+                            &hint,
+                            media_source_stream,
+                            &format_opts,
+                            &metadata_opts,
+                        ) {
+                            Ok(probed) => probed,
+                            Err(err) => {
+                                failed(err.to_string());
+                                continue;
+                            }
+                        };
 
                         let mut format = probed.format;
 
                         // Finds the first decodable track
-                        let track = format
+                        let track = match format
                             .tracks()
                             .iter()
                             .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
-                            .expect("No supported audio track");
+                        {
+                            Some(track) => track,
+                            None => {
+                                failed("no supported audio track".to_string());
+                                continue;
+                            }
+                        };
 
                         let track_id = track.id;
 
@@ -121,17 +197,53 @@ impl Player for SymphoniaPlayer {
                             _ => return,
                         };
 
-                        // TODO: have these .expects be errors that are returned and displayed
                         // TODO: user configurable option for how errors are displayed (popup or printed at the bottom)
-                        let mut decoder = symphonia::default::get_codecs()
+                        let mut decoder = match symphonia::default::get_codecs()
                             .make(&track.codec_params, &dec_opts)
-                            .expect("unsupported codec");
+                        {
+                            Ok(decoder) => decoder,
+                            Err(err) => {
+                                failed(err.to_string());
+                                continue;
+                            }
+                        };
+
+                        let start = match request {
+                            PlayerRequests::Restore(progress) => {
+                                app_state.lock().unwrap().player.curr_state = PlayerStates::PAUSED;
+                                let seek_to = SeekTo::Time {
+                                    time: Time::from(progress.as_secs_f64()),
+                                    track_id: Some(track_id),
+                                };
+                                match format.seek(SeekMode::Accurate, seek_to) {
+                                    Ok(_) => {
+                                        decoder.reset();
+                                        progress
+                                    }
+                                    Err(err) => {
+                                        warn!("Could not seek in {}: {}", song.path, err);
+                                        Duration::ZERO
+                                    }
+                                }
+                            }
+                            _ => Duration::ZERO,
+                        };
 
                         let cloned_state = app_state.clone();
+                        let cloned_main_tx = main_tx.clone();
+                        let path = song.path.to_owned();
 
                         // spin up another thread that will start playing audio
                         join_handle = Some(std::thread::spawn(move || {
-                            player(cloned_state, &mut format, track_id, &mut decoder)
+                            player(
+                                cloned_state,
+                                cloned_main_tx,
+                                path,
+                                start,
+                                &mut format,
+                                track_id,
+                                &mut decoder,
+                            )
                         }));
                     }
                 },
@@ -150,17 +262,21 @@ impl Player for SymphoniaPlayer {
 
 fn player(
     app_state: Arc<Mutex<AppState>>,
+    main_tx: Sender<AppRequests>,
+    path: String,
+    start: Duration,
     format: &mut Box<dyn FormatReader>,
     track_id: u32,
     decoder: &mut Box<dyn Decoder>,
 ) {
     let mut audio_output = None;
-    app_state.lock().unwrap().player.progress = Duration::ZERO;
+    app_state.lock().unwrap().player.progress = start;
 
     loop {
         match app_state.lock().unwrap().player.curr_state {
             PlayerStates::STOPPED => break,
             PlayerStates::PAUSED => {
+                std::thread::sleep(Duration::from_millis(100));
                 continue;
             }
             _ => (),
@@ -168,7 +284,12 @@ fn player(
 
         let packet = match format.next_packet() {
             Ok(packet) => packet,
-            Err(..) => return,
+            // end of the stream
+            Err(..) => {
+                let request = PlayerRequests::SongEnded(path);
+                let _ = main_tx.send(AppRequests::PlayerRequests(request));
+                return;
+            }
         };
 
         if packet.track_id() != track_id {
@@ -189,6 +310,7 @@ fn player(
                                                    //of times
                                                    // update player time with how long the last packet took to play
         guard.player.progress = guard.player.progress + start_packet_time.elapsed();
+        guard.player.listened += start_packet_time.elapsed();
         drop(guard);
     }
 }
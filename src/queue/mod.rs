@@ -1,19 +1,109 @@
+pub mod session;
+
 use crate::library::song::Song;
 
-use std::collections::VecDeque;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShuffleMode {
+    #[default]
+    Off,
+    Tracks,
+    // random albums, the tracks of each album in order
+    Albums,
+    // tracks that were already played while shuffling come after the others, until every
+    // track has been played
+    NoRepeat,
+}
+
+impl ShuffleMode {
+    pub fn next(&self) -> ShuffleMode {
+        match self {
+            ShuffleMode::Off => ShuffleMode::Tracks,
+            ShuffleMode::Tracks => ShuffleMode::Albums,
+            ShuffleMode::Albums => ShuffleMode::NoRepeat,
+            ShuffleMode::NoRepeat => ShuffleMode::Off,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShuffleMode::Off => "off",
+            ShuffleMode::Tracks => "tracks",
+            ShuffleMode::Albums => "albums",
+            ShuffleMode::NoRepeat => "no repeat",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    One,
+    // the songs played since the queue was replaced start over when it runs out
+    All,
+}
+
+impl RepeatMode {
+    pub fn next(&self) -> RepeatMode {
+        match self {
+            RepeatMode::Off => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::Off,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RepeatMode::Off => "off",
+            RepeatMode::One => "one",
+            RepeatMode::All => "all",
+        }
+    }
+}
 
 pub struct SongQueue {
     previous_queue: VecDeque<Song>,
     immediate_queue: VecDeque<Song>,
     upcoming_queue: VecDeque<Song>,
+    // start of the songs in `previous_queue` played since the queue was last cleared
+    cycle_start: usize,
+    repeat: RepeatMode,
+    // similar songs are added when the queue runs out
+    radio: bool,
+    shuffle: ShuffleMode,
+    // upcoming songs in the order they were added, to undo the shuffle
+    unshuffled: Vec<Song>,
+    // paths played since no-repeat shuffle was turned on
+    played: HashSet<String>,
+    rng: StdRng,
 }
 
 impl SongQueue {
     pub fn new() -> SongQueue {
+        SongQueue::with_seed(rand::random())
+    }
+
+    // the same seed and songs give the same shuffled order
+    pub fn with_seed(seed: u64) -> SongQueue {
         SongQueue {
             previous_queue: VecDeque::new(),
             immediate_queue: VecDeque::new(),
             upcoming_queue: VecDeque::new(),
+            cycle_start: 0,
+            repeat: RepeatMode::Off,
+            radio: false,
+            shuffle: ShuffleMode::Off,
+            unshuffled: Vec::new(),
+            played: HashSet::new(),
+            rng: StdRng::seed_from_u64(seed),
         }
     }
 
@@ -25,19 +115,509 @@ impl SongQueue {
         self.immediate_queue.push_back(song);
     }
 
+    // While shuffling tracks the song goes to a random position, album shuffle appends it so
+    // albums added a track at a time stay together
     pub fn add_upcoming(&mut self, song: Song) {
-        self.upcoming_queue.push_back(song);
+        if self.shuffle != ShuffleMode::Off {
+            self.unshuffled.push(song.clone());
+        }
+        match self.shuffle {
+            ShuffleMode::Tracks => {
+                let idx = self.rng.gen_range(0..=self.upcoming_queue.len());
+                self.upcoming_queue.insert(idx, song);
+            }
+            ShuffleMode::NoRepeat => {
+                // among the unplayed songs, which come first
+                let unplayed = self
+                    .upcoming_queue
+                    .iter()
+                    .take_while(|s| !self.played.contains(&s.path))
+                    .count();
+                let idx = match self.played.contains(&song.path) {
+                    true => self.rng.gen_range(unplayed..=self.upcoming_queue.len()),
+                    false => self.rng.gen_range(0..=unplayed),
+                };
+                self.upcoming_queue.insert(idx, song);
+            }
+            ShuffleMode::Off | ShuffleMode::Albums => self.upcoming_queue.push_back(song),
+        }
+    }
+
+    // replace the upcoming songs, shuffled if a shuffle mode is on
+    pub fn set_upcoming(&mut self, songs: Vec<Song>) {
+        self.upcoming_queue = songs.into();
+        self.unshuffled.clear();
+        if self.shuffle != ShuffleMode::Off {
+            self.unshuffled = self.upcoming_queue.iter().cloned().collect();
+            self.shuffle_upcoming();
+        }
+    }
+
+    // drop every song that hasn't been played yet
+    pub fn clear(&mut self) {
+        self.immediate_queue.clear();
+        self.upcoming_queue.clear();
+        self.unshuffled.clear();
+        self.cycle_start = self.previous_queue.len();
     }
 
     pub fn add_to_previous(&mut self, song: Song) {
         self.previous_queue.push_back(song);
     }
 
+    // songs queued to play next, before the upcoming ones
+    pub fn immediate(&self) -> &VecDeque<Song> {
+        &self.immediate_queue
+    }
+
+    pub fn upcoming(&self) -> &VecDeque<Song> {
+        &self.upcoming_queue
+    }
+
+    // Positions count the immediate songs first, then the upcoming ones, as they will be played
+    pub fn len(&self) -> usize {
+        self.immediate_queue.len() + self.upcoming_queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, pos: usize) -> Option<&Song> {
+        match pos.checked_sub(self.immediate_queue.len()) {
+            Some(idx) => self.upcoming_queue.get(idx),
+            None => self.immediate_queue.get(pos),
+        }
+    }
+
+    pub fn remove(&mut self, pos: usize) -> Option<Song> {
+        let (queue, idx) = self.locate(pos)?;
+        queue.remove(idx)
+    }
+
+    // Moves the song at `pos` one place up or down within its part of the queue, returns its
+    // new position
+    pub fn move_song(&mut self, pos: usize, up: bool) -> Option<usize> {
+        let (queue, idx) = self.locate(pos)?;
+        let to = match up {
+            true => idx.checked_sub(1)?,
+            false => Some(idx + 1).filter(|to| *to < queue.len())?,
+        };
+        queue.swap(idx, to);
+        Some(pos + to - idx)
+    }
+
+    // Makes the song at `pos` the next one to play. The upcoming songs before it are skipped
+    // and count as played, songs queued to play next stay in the queue.
+    pub fn jump(&mut self, pos: usize) -> bool {
+        let song = match pos.checked_sub(self.immediate_queue.len()) {
+            Some(idx) if idx < self.upcoming_queue.len() => {
+                let skipped: Vec<Song> = self.upcoming_queue.drain(..idx).collect();
+                self.previous_queue.extend(skipped);
+                self.upcoming_queue.pop_front()
+            }
+            Some(_) => None,
+            None => self.immediate_queue.remove(pos),
+        };
+        match song {
+            Some(song) => {
+                self.immediate_queue.push_front(song);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    pub fn set_repeat(&mut self, mode: RepeatMode) {
+        self.repeat = mode;
+    }
+
+    pub fn radio(&self) -> bool {
+        self.radio
+    }
+
+    pub fn set_radio(&mut self, radio: bool) {
+        self.radio = radio;
+    }
+
+    // the last `count` songs played, most recent first
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &Song> {
+        self.previous_queue.iter().rev().take(count)
+    }
+
+    pub fn shuffle(&self) -> ShuffleMode {
+        self.shuffle
+    }
+
+    // Shuffles the upcoming songs, or puts them back in the order they were added when turned
+    // off. Songs that were queued to play next aren't shuffled.
+    pub fn set_shuffle(&mut self, mode: ShuffleMode) {
+        if mode == self.shuffle {
+            return;
+        }
+        if self.shuffle != ShuffleMode::Off {
+            self.unshuffle();
+        }
+        self.shuffle = mode;
+        self.played.clear();
+        if mode != ShuffleMode::Off {
+            self.unshuffled = self.upcoming_queue.iter().cloned().collect();
+            self.shuffle_upcoming();
+        }
+    }
+
+    // The song to play after the current one, which has to be added to the previous songs
+    // first. Repeat-one plays it again.
     pub fn next(&mut self) -> Option<Song> {
-        if self.immediate_queue.is_empty() {
-            return self.upcoming_queue.pop_front();
-        } else {
-            return self.immediate_queue.pop_front();
+        if self.repeat == RepeatMode::One {
+            if let Some(song) = self.previous_queue.pop_back() {
+                self.cycle_start = self.cycle_start.min(self.previous_queue.len());
+                return Some(song);
+            }
+        }
+        self.skip()
+    }
+
+    // like `next` but moves on from the current song even when repeating it
+    pub fn skip(&mut self) -> Option<Song> {
+        if self.repeat == RepeatMode::All
+            && self.immediate_queue.is_empty()
+            && self.upcoming_queue.is_empty()
+        {
+            let start = self.cycle_start.min(self.previous_queue.len());
+            let songs = self.previous_queue.drain(start..).collect();
+            self.set_upcoming(songs);
+        }
+        let song = match self.immediate_queue.is_empty() {
+            true => self.upcoming_queue.pop_front(),
+            false => self.immediate_queue.pop_front(),
+        };
+        if let Some(song) = &song {
+            if self.shuffle == ShuffleMode::NoRepeat {
+                self.played.insert(song.path.to_owned());
+            }
+        }
+        song
+    }
+
+    // the part of the queue with the song at `pos` and its index there
+    fn locate(&mut self, pos: usize) -> Option<(&mut VecDeque<Song>, usize)> {
+        match pos.checked_sub(self.immediate_queue.len()) {
+            Some(idx) if idx < self.upcoming_queue.len() => Some((&mut self.upcoming_queue, idx)),
+            Some(_) => None,
+            None => Some((&mut self.immediate_queue, pos)),
+        }
+    }
+
+    fn shuffle_upcoming(&mut self) {
+        let mut songs: Vec<Song> = self.upcoming_queue.drain(..).collect();
+        match self.shuffle {
+            ShuffleMode::Off => (),
+            ShuffleMode::Tracks => songs.shuffle(&mut self.rng),
+            ShuffleMode::Albums => songs = self.shuffle_albums(songs),
+            ShuffleMode::NoRepeat => {
+                // every song was played, start over
+                if songs.iter().all(|song| self.played.contains(&song.path)) {
+                    self.played.clear();
+                }
+                let (mut unplayed, mut played): (Vec<Song>, Vec<Song>) = songs
+                    .into_iter()
+                    .partition(|song| !self.played.contains(&song.path));
+                unplayed.shuffle(&mut self.rng);
+                played.shuffle(&mut self.rng);
+                unplayed.append(&mut played);
+                songs = unplayed;
+            }
+        }
+        self.upcoming_queue = songs.into();
+    }
+
+    fn shuffle_albums(&mut self, songs: Vec<Song>) -> Vec<Song> {
+        let mut albums: Vec<Vec<Song>> = Vec::new();
+        let mut positions: HashMap<(String, String), usize> = HashMap::new();
+        for song in songs {
+            let key = (song.album_artist.to_owned(), song.album_title.to_owned());
+            let idx = *positions.entry(key).or_insert_with(|| {
+                albums.push(Vec::new());
+                albums.len() - 1
+            });
+            albums[idx].push(song);
+        }
+        albums.shuffle(&mut self.rng);
+        for album in albums.iter_mut() {
+            album.sort_by_key(|song| song.track_position());
+        }
+        albums.into_iter().flatten().collect()
+    }
+
+    // the upcoming songs in the order they were added, without the ones already played
+    fn unshuffle(&mut self) {
+        let mut remaining: HashMap<&str, usize> = HashMap::new();
+        for song in self.upcoming_queue.iter() {
+            *remaining.entry(&song.path).or_default() += 1;
+        }
+        let mut upcoming = VecDeque::new();
+        for song in self.unshuffled.drain(..) {
+            if let Some(count) = remaining.get_mut(song.path.as_str()) {
+                if *count > 0 {
+                    *count -= 1;
+                    upcoming.push_back(song);
+                }
+            }
         }
+        self.upcoming_queue = upcoming;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn songs(count: usize) -> Vec<Song> {
+        (0..count)
+            .map(|idx| Song::new(format!("song {}", idx), format!("/music/{}.mp3", idx)))
+            .collect()
+    }
+
+    fn paths(queue: &VecDeque<Song>) -> Vec<&str> {
+        queue.iter().map(|song| song.path.as_str()).collect()
+    }
+
+    // takes the next song and adds it to the previous ones, as the player does
+    fn play(queue: &mut SongQueue, skip: bool) -> Option<String> {
+        let song = match skip {
+            true => queue.skip(),
+            false => queue.next(),
+        }?;
+        let path = song.path.to_owned();
+        queue.add_to_previous(song);
+        Some(path)
+    }
+
+    // "next" and "after" queued to play next, then songs 0 to 2
+    fn queue_with_immediate() -> SongQueue {
+        let mut queue = SongQueue::with_seed(7);
+        queue.set_upcoming(songs(3));
+        queue.add_last_immediate(Song::new("next".to_string(), "/music/next.mp3".to_string()));
+        queue.add_last_immediate(Song::new(
+            "after".to_string(),
+            "/music/after.mp3".to_string(),
+        ));
+        queue
+    }
+
+    fn all_paths(queue: &SongQueue) -> Vec<&str> {
+        (0..queue.len())
+            .map(|pos| queue.get(pos).unwrap().path.as_str())
+            .collect()
+    }
+
+    fn shuffled(seed: u64) -> Vec<String> {
+        let mut queue = SongQueue::with_seed(seed);
+        queue.set_upcoming(songs(20));
+        queue.set_shuffle(ShuffleMode::Tracks);
+        queue
+            .upcoming()
+            .iter()
+            .map(|song| song.path.to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn shuffle_is_deterministic_for_a_seed() {
+        assert_eq!(shuffled(7), shuffled(7));
+        assert_ne!(shuffled(7), shuffled(8));
+    }
+
+    #[test]
+    fn unshuffle_restores_the_order() {
+        let mut queue = SongQueue::with_seed(7);
+        queue.set_upcoming(songs(20));
+        let original: Vec<String> = paths(queue.upcoming())
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        queue.set_shuffle(ShuffleMode::Tracks);
+        assert_ne!(paths(queue.upcoming()), original);
+        queue.set_shuffle(ShuffleMode::Off);
+        assert_eq!(paths(queue.upcoming()), original);
+    }
+
+    #[test]
+    fn unshuffle_leaves_out_played_songs() {
+        let mut queue = SongQueue::with_seed(7);
+        queue.set_upcoming(songs(5));
+        queue.set_shuffle(ShuffleMode::Tracks);
+        let played = queue.skip().unwrap();
+        queue.set_shuffle(ShuffleMode::Off);
+        let expected: Vec<String> = songs(5)
+            .into_iter()
+            .map(|song| song.path)
+            .filter(|path| *path != played.path)
+            .collect();
+        assert_eq!(paths(queue.upcoming()), expected);
+    }
+
+    #[test]
+    fn immediate_songs_play_before_shuffled_ones() {
+        let mut queue = SongQueue::with_seed(7);
+        queue.set_upcoming(songs(10));
+        queue.add_first_immediate(Song::new("next".to_string(), "/music/next.mp3".to_string()));
+        queue.add_last_immediate(Song::new(
+            "after".to_string(),
+            "/music/after.mp3".to_string(),
+        ));
+        queue.set_shuffle(ShuffleMode::Tracks);
+        queue.add_upcoming(Song::new("new".to_string(), "/music/new.mp3".to_string()));
+        assert_eq!(
+            paths(queue.immediate()),
+            ["/music/next.mp3", "/music/after.mp3"]
+        );
+        assert_eq!(queue.skip().unwrap().path, "/music/next.mp3");
+        assert_eq!(queue.skip().unwrap().path, "/music/after.mp3");
+        assert_eq!(queue.len(), 11);
+    }
+
+    #[test]
+    fn repeat_all_runs_out_when_no_song_could_be_played() {
+        let mut queue = SongQueue::with_seed(7);
+        queue.set_upcoming(songs(3));
+        queue.set_repeat(RepeatMode::All);
+        // the first song plays, the others fail and aren't added to the previous songs
+        let played = queue.skip().unwrap();
+        queue.add_to_previous(played);
+        assert!(queue.skip().is_some());
+        assert!(queue.skip().is_some());
+        assert_eq!(queue.skip().unwrap().path, "/music/0.mp3");
+        // it fails too this time
+        assert!(queue.skip().is_none());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn repeat_one_plays_the_song_again_until_skipped() {
+        let mut queue = SongQueue::with_seed(7);
+        queue.set_upcoming(songs(2));
+        queue.set_repeat(RepeatMode::One);
+        assert_eq!(play(&mut queue, false).unwrap(), "/music/0.mp3");
+        assert_eq!(play(&mut queue, false).unwrap(), "/music/0.mp3");
+        assert_eq!(play(&mut queue, true).unwrap(), "/music/1.mp3");
+        assert_eq!(play(&mut queue, false).unwrap(), "/music/1.mp3");
+        assert_eq!(queue.recent(10).count(), 2);
+        // nothing left to skip to
+        assert!(play(&mut queue, true).is_none());
+    }
+
+    #[test]
+    fn repeat_all_starts_over_from_the_songs_since_the_queue_was_replaced() {
+        let mut queue = SongQueue::with_seed(7);
+        queue.add_to_previous(Song::new("old".to_string(), "/music/old.mp3".to_string()));
+        queue.clear();
+        queue.set_upcoming(songs(2));
+        queue.set_repeat(RepeatMode::All);
+        let played: Vec<String> = (0..5).filter_map(|_| play(&mut queue, false)).collect();
+        assert_eq!(
+            played,
+            [
+                "/music/0.mp3",
+                "/music/1.mp3",
+                "/music/0.mp3",
+                "/music/1.mp3",
+                "/music/0.mp3"
+            ]
+        );
+        // the songs played before stay in the history
+        assert_eq!(queue.recent(10).last().unwrap().path, "/music/old.mp3");
+    }
+
+    #[test]
+    fn repeat_turned_on_in_the_middle_of_the_queue() {
+        let mut queue = SongQueue::with_seed(7);
+        queue.set_upcoming(songs(3));
+        play(&mut queue, false);
+        play(&mut queue, false);
+        queue.set_repeat(RepeatMode::One);
+        assert_eq!(play(&mut queue, false).unwrap(), "/music/1.mp3");
+        // songs played before repeat-all was turned on are part of the cycle
+        queue.set_repeat(RepeatMode::All);
+        assert_eq!(play(&mut queue, false).unwrap(), "/music/2.mp3");
+        assert_eq!(play(&mut queue, false).unwrap(), "/music/0.mp3");
+        assert_eq!(play(&mut queue, false).unwrap(), "/music/1.mp3");
+        queue.set_repeat(RepeatMode::Off);
+        assert_eq!(play(&mut queue, false).unwrap(), "/music/2.mp3");
+        assert!(play(&mut queue, false).is_none());
+    }
+
+    #[test]
+    fn positions_span_both_parts_of_the_queue() {
+        let mut queue = queue_with_immediate();
+        assert_eq!(queue.get(1).unwrap().path, "/music/after.mp3");
+        assert_eq!(queue.get(2).unwrap().path, "/music/0.mp3");
+        assert!(queue.get(5).is_none());
+        assert!(queue.remove(5).is_none());
+        assert_eq!(queue.remove(2).unwrap().path, "/music/0.mp3");
+        assert_eq!(queue.remove(1).unwrap().path, "/music/after.mp3");
+        assert_eq!(
+            all_paths(&queue),
+            ["/music/next.mp3", "/music/1.mp3", "/music/2.mp3"]
+        );
+    }
+
+    #[test]
+    fn songs_move_within_their_part_of_the_queue() {
+        let mut queue = queue_with_immediate();
+        // the first and last songs can't move past the ends
+        assert_eq!(queue.move_song(0, true), None);
+        assert_eq!(queue.move_song(4, false), None);
+        // nor across the boundary between the two parts
+        assert_eq!(queue.move_song(1, false), None);
+        assert_eq!(queue.move_song(2, true), None);
+        assert_eq!(queue.move_song(5, true), None);
+
+        assert_eq!(queue.move_song(0, false), Some(1));
+        assert_eq!(queue.move_song(4, true), Some(3));
+        assert_eq!(queue.move_song(2, false), Some(3));
+        assert_eq!(
+            all_paths(&queue),
+            [
+                "/music/after.mp3",
+                "/music/next.mp3",
+                "/music/2.mp3",
+                "/music/0.mp3",
+                "/music/1.mp3"
+            ]
+        );
+    }
+
+    #[test]
+    fn jumping_skips_the_upcoming_songs_before() {
+        let mut queue = queue_with_immediate();
+        assert!(!queue.jump(5));
+        assert!(queue.jump(1));
+        assert_eq!(
+            paths(queue.immediate()),
+            ["/music/after.mp3", "/music/next.mp3"]
+        );
+        assert!(queue.recent(10).next().is_none());
+
+        assert!(queue.jump(3));
+        assert_eq!(
+            all_paths(&queue),
+            [
+                "/music/1.mp3",
+                "/music/after.mp3",
+                "/music/next.mp3",
+                "/music/2.mp3"
+            ]
+        );
+        assert_eq!(queue.recent(10).next().unwrap().path, "/music/0.mp3");
+        // the last position
+        assert!(queue.jump(3));
+        assert_eq!(queue.get(0).unwrap().path, "/music/2.mp3");
+        assert!(queue.upcoming().is_empty());
     }
 }
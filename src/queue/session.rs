@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::{RepeatMode, ShuffleMode, SongQueue};
+use crate::library::playlist;
+use crate::library::song::Song;
+use crate::library::Library;
+use crate::state::AppState;
+
+// The queue and the song being played, saved as paths on quit and every so often so the next
+// session can pick up where this one stopped
+#[derive(Default, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    current: Option<String>,
+    progress_ms: u64,
+    // how long the current song was listened to, so it's counted as played once it ends
+    #[serde(default)]
+    listened_ms: u64,
+    shuffle: ShuffleMode,
+    repeat: RepeatMode,
+    #[serde(default)]
+    radio: bool,
+    cycle_start: usize,
+    previous: Vec<String>,
+    immediate: Vec<String>,
+    upcoming: Vec<String>,
+    // order of the upcoming songs before they were shuffled
+    unshuffled: Vec<String>,
+}
+
+impl Session {
+    pub fn new(state: &AppState) -> Session {
+        let queue = &state.queue;
+        Session {
+            current: state
+                .player
+                .curr_song
+                .as_ref()
+                .map(|song| song.path.to_owned()),
+            progress_ms: state.player.progress.as_millis() as u64,
+            listened_ms: state.player.listened.as_millis() as u64,
+            shuffle: queue.shuffle,
+            repeat: queue.repeat,
+            radio: queue.radio,
+            cycle_start: queue.cycle_start,
+            previous: paths(&queue.previous_queue),
+            immediate: paths(&queue.immediate_queue),
+            upcoming: paths(&queue.upcoming_queue),
+            unshuffled: paths(&queue.unshuffled),
+        }
+    }
+
+    pub fn load() -> Option<Session> {
+        let path = session_path();
+        let text = fs::read_to_string(&path).ok()?;
+        match toml::from_str(&text) {
+            Ok(session) => Some(session),
+            Err(err) => {
+                error!("Could not parse {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = session_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    // Puts the saved queue and current song in `state`, returns the position to resume the song
+    // from. Songs that are no longer in the library are left out.
+    pub fn restore(&self, state: &mut AppState) -> Option<Duration> {
+        let library = &state.library;
+        let mut queue = SongQueue::new();
+        queue.shuffle = self.shuffle;
+        queue.repeat = self.repeat;
+        queue.radio = self.radio;
+        queue.previous_queue = songs(&self.previous, library).collect();
+        queue.cycle_start = self.cycle_start.min(queue.previous_queue.len());
+        queue.immediate_queue = songs(&self.immediate, library).collect();
+        queue.upcoming_queue = songs(&self.upcoming, library).collect();
+        queue.unshuffled = songs(&self.unshuffled, library).collect();
+        let current = self
+            .current
+            .as_deref()
+            .and_then(|path| library.song(path))
+            .cloned();
+        state.queue = queue;
+        state.player.curr_song = Some(current?);
+        state.player.progress = Duration::from_millis(self.progress_ms);
+        state.player.listened = Duration::from_millis(self.listened_ms);
+        Some(state.player.progress)
+    }
+}
+
+fn paths<'a>(songs: impl IntoIterator<Item = &'a Song>) -> Vec<String> {
+    songs.into_iter().map(|song| song.path.to_owned()).collect()
+}
+
+fn songs<'a>(paths: &'a [String], library: &'a Library) -> impl Iterator<Item = Song> + 'a {
+    paths.iter().filter_map(|path| library.song(path)).cloned()
+}
+
+fn session_path() -> PathBuf {
+    playlist::data_dir().join("session.toml")
+}
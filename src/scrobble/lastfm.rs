@@ -0,0 +1,125 @@
+use serde_json::Value;
+
+use super::{ScrobbleError, Service};
+use crate::config::LastFmConfig;
+use crate::library::history::Play;
+use crate::library::song::Song;
+
+pub const DEFAULT_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+// errors for requests that won't be accepted however often they're sent: invalid parameters
+// and invalid resource, the others are about the session, the API key or the service
+const PERMANENT_ERRORS: [u64; 2] = [6, 7];
+
+// https://www.last.fm/api/scrobbling, with a session key from the desktop or web auth flow
+pub struct LastFm {
+    url: String,
+    api_key: String,
+    secret: String,
+    session_key: String,
+    agent: ureq::Agent,
+}
+
+impl LastFm {
+    pub fn new(config: &LastFmConfig, agent: ureq::Agent) -> LastFm {
+        LastFm {
+            url: config.url.to_owned(),
+            api_key: config.api_key.to_owned(),
+            secret: config.secret.to_owned(),
+            session_key: config.session_key.to_owned(),
+            agent,
+        }
+    }
+
+    // signed POST of `method` with `params`
+    fn call(&self, method: &str, mut params: Vec<(String, String)>) -> Result<(), ScrobbleError> {
+        params.push(("method".to_string(), method.to_string()));
+        params.push(("api_key".to_string(), self.api_key.to_owned()));
+        params.push(("sk".to_string(), self.session_key.to_owned()));
+        // the signature is the md5 of the sorted parameters followed by the secret
+        params.sort();
+        let mut signature: String = params
+            .iter()
+            .map(|(key, value)| key.to_owned() + value)
+            .collect();
+        signature.push_str(&self.secret);
+        params.push((
+            "api_sig".to_string(),
+            format!("{:x}", md5::compute(signature)),
+        ));
+        params.push(("format".to_string(), "json".to_string()));
+
+        let form: Vec<(&str, &str)> = params
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let response = self
+            .agent
+            .post(&self.url)
+            .send_form(&form)
+            .map_err(|err| ScrobbleError::from_ureq(err, |_, body| is_permanent(body)))?;
+        // errors can also come back with a 200
+        let status = response.status();
+        let body = response.into_string().unwrap_or_default();
+        match serde_json::from_str::<Value>(&body) {
+            Ok(json) if json.get("error").is_some() => Err(ScrobbleError::Rejected {
+                status,
+                permanent: is_permanent(&body),
+                message: body,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Service for LastFm {
+    fn name(&self) -> &'static str {
+        "lastfm"
+    }
+
+    fn batch_size(&self) -> usize {
+        50
+    }
+
+    fn now_playing(&self, song: &Song) -> Result<(), ScrobbleError> {
+        let mut params = vec![
+            ("artist".to_string(), song.track_artist.to_owned()),
+            ("track".to_string(), song.title.to_owned()),
+            (
+                "duration".to_string(),
+                (song.duration_ms / 1000).to_string(),
+            ),
+        ];
+        if !song.album_title.is_empty() {
+            params.push(("album".to_string(), song.album_title.to_owned()));
+        }
+        self.call("track.updateNowPlaying", params)
+    }
+
+    fn submit(&self, plays: &[Play]) -> Result<(), ScrobbleError> {
+        let mut params = Vec::new();
+        for (idx, play) in plays.iter().enumerate() {
+            let mut param = |key: &str, value: String| {
+                params.push((format!("{}[{}]", key, idx), value));
+            };
+            param("artist", play.artist.to_owned());
+            param("track", play.title.to_owned());
+            param("timestamp", play.time.to_string());
+            param("duration", (play.duration_ms / 1000).to_string());
+            if !play.album.is_empty() {
+                param("album", play.album.to_owned());
+            }
+            if !play.album_artist.is_empty() {
+                param("albumArtist", play.album_artist.to_owned());
+            }
+        }
+        self.call("track.scrobble", params)
+    }
+}
+
+fn is_permanent(body: &str) -> bool {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|json| json.get("error")?.as_u64())
+        .is_some_and(|code| PERMANENT_ERRORS.contains(&code))
+}
@@ -0,0 +1,99 @@
+use serde_json::{json, Value};
+
+use super::{ScrobbleError, Service};
+use crate::config::ListenBrainzConfig;
+use crate::library::history::Play;
+use crate::library::song::Song;
+
+pub const DEFAULT_URL: &str = "https://api.listenbrainz.org";
+
+// https://listenbrainz.readthedocs.io/en/latest/users/api/core.html#post--1-submit-listens
+pub struct ListenBrainz {
+    url: String,
+    token: String,
+    agent: ureq::Agent,
+}
+
+impl ListenBrainz {
+    pub fn new(config: &ListenBrainzConfig, agent: ureq::Agent) -> ListenBrainz {
+        ListenBrainz {
+            url: config.url.trim_end_matches('/').to_string(),
+            token: config.token.to_owned(),
+            agent,
+        }
+    }
+
+    fn post(&self, body: Value) -> Result<(), ScrobbleError> {
+        self.agent
+            .post(&format!("{}/1/submit-listens", self.url))
+            .set("Authorization", &format!("Token {}", self.token))
+            .send_json(body)
+            .map(|_| ())
+            // a bad request won't get better, anything else (bad token, rate limits,
+            // server errors) can
+            .map_err(|err| ScrobbleError::from_ureq(err, |status, _| status == 400))
+    }
+}
+
+impl Service for ListenBrainz {
+    fn name(&self) -> &'static str {
+        "listenbrainz"
+    }
+
+    fn batch_size(&self) -> usize {
+        // the API takes up to 1000 listens a request but limits their total size
+        100
+    }
+
+    fn now_playing(&self, song: &Song) -> Result<(), ScrobbleError> {
+        let metadata = track_metadata(
+            &song.track_artist,
+            &song.title,
+            &song.album_title,
+            song.duration_ms,
+        );
+        self.post(json!({
+            "listen_type": "playing_now",
+            "payload": [{ "track_metadata": metadata }],
+        }))
+    }
+
+    fn submit(&self, plays: &[Play]) -> Result<(), ScrobbleError> {
+        let payload: Vec<Value> = plays
+            .iter()
+            .map(|play| {
+                json!({
+                    "listened_at": play.time,
+                    "track_metadata": track_metadata(
+                        &play.artist,
+                        &play.title,
+                        &play.album,
+                        play.duration_ms
+                    ),
+                })
+            })
+            .collect();
+        let listen_type = match plays.len() {
+            1 => "single",
+            _ => "import",
+        };
+        self.post(json!({ "listen_type": listen_type, "payload": payload }))
+    }
+}
+
+fn track_metadata(artist: &str, title: &str, album: &str, duration_ms: u64) -> Value {
+    let mut metadata = json!({
+        "artist_name": artist,
+        "track_name": title,
+        "additional_info": {
+            "duration_ms": duration_ms,
+            "media_player": "splay",
+            "submission_client": "splay",
+            "submission_client_version": env!("CARGO_PKG_VERSION"),
+        },
+    });
+    if !album.is_empty() {
+        metadata["release_name"] = json!(album);
+    }
+    metadata
+}
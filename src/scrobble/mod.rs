@@ -0,0 +1,368 @@
+pub mod lastfm;
+pub mod listenbrainz;
+mod pending;
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::config::ScrobbleConfig;
+use crate::library::history::Play;
+use crate::library::song::Song;
+use lastfm::LastFm;
+use listenbrainz::ListenBrainz;
+use pending::PendingScrobbles;
+
+// shorter songs aren't scrobbled
+const MIN_DURATION: Duration = Duration::from_secs(30);
+// wait before sending again after a service couldn't be reached, doubled every time it fails
+const FIRST_RETRY: Duration = Duration::from_secs(30);
+const MAX_RETRY: Duration = Duration::from_secs(3600);
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Error)]
+pub enum ScrobbleError {
+    #[error("could not connect ({0})")]
+    Unreachable(String),
+    #[error("rejected with status {status}: {message}")]
+    Rejected {
+        status: u16,
+        message: String,
+        // the request is wrong and sending it again won't help
+        permanent: bool,
+    },
+}
+
+impl ScrobbleError {
+    // `permanent` tells from the status and body whether the request shouldn't be sent again
+    fn from_ureq(err: ureq::Error, permanent: fn(u16, &str) -> bool) -> ScrobbleError {
+        match err {
+            ureq::Error::Status(status, response) => {
+                let message = response.into_string().unwrap_or_default();
+                ScrobbleError::Rejected {
+                    status,
+                    permanent: permanent(status, &message),
+                    message,
+                }
+            }
+            ureq::Error::Transport(transport) => ScrobbleError::Unreachable(transport.to_string()),
+        }
+    }
+
+    fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            ScrobbleError::Rejected {
+                permanent: true,
+                ..
+            }
+        )
+    }
+}
+
+// A scrobbling service, called from the scrobbler thread
+pub trait Service: Send {
+    // also names the service in the pending scrobbles
+    fn name(&self) -> &'static str;
+    // most listens sent in one request
+    fn batch_size(&self) -> usize;
+    fn now_playing(&self, song: &Song) -> Result<(), ScrobbleError>;
+    fn submit(&self, plays: &[Play]) -> Result<(), ScrobbleError>;
+}
+
+pub enum ScrobbleRequest {
+    NowPlaying(Box<Song>),
+    Listen(Play),
+}
+
+// Handle to the thread that scrobbles to the services set up in the config. Listens are saved
+// to disk before they're sent, so they're kept until the services can be reached.
+pub struct Scrobbler {
+    tx: Sender<ScrobbleRequest>,
+}
+
+impl Scrobbler {
+    // None if no service is set up
+    pub fn start(config: &ScrobbleConfig) -> Option<Scrobbler> {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(TIMEOUT)
+            .user_agent(concat!("splay/", env!("CARGO_PKG_VERSION")))
+            .build();
+        let mut services: Vec<Box<dyn Service>> = Vec::new();
+        if let Some(config) = &config.listenbrainz {
+            services.push(Box::new(ListenBrainz::new(config, agent.clone())));
+        }
+        if let Some(config) = &config.lastfm {
+            services.push(Box::new(LastFm::new(config, agent)));
+        }
+        if services.is_empty() {
+            return None;
+        }
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || Worker::new(services, PendingScrobbles::load()).run(rx));
+        Some(Scrobbler { tx })
+    }
+
+    pub fn now_playing(&self, song: &Song) {
+        if Duration::from_millis(song.duration_ms) > MIN_DURATION {
+            let _ = self
+                .tx
+                .send(ScrobbleRequest::NowPlaying(Box::new(song.clone())));
+        }
+    }
+
+    // `play` already listened to long enough to count
+    pub fn listen(&self, play: &Play) {
+        // without a duration the time listened is all there is to go by
+        let duration = match play.duration_ms {
+            0 => play.listened_ms,
+            duration => duration,
+        };
+        if Duration::from_millis(duration) > MIN_DURATION {
+            let _ = self.tx.send(ScrobbleRequest::Listen(play.clone()));
+        }
+    }
+}
+
+#[derive(Default)]
+struct Backoff {
+    delay: Duration,
+    retry_at: Option<Instant>,
+}
+
+impl Backoff {
+    fn waiting(&self) -> bool {
+        self.retry_at.is_some_and(|at| at > Instant::now())
+    }
+
+    fn failed(&mut self) -> Duration {
+        self.delay = (self.delay * 2).clamp(FIRST_RETRY, MAX_RETRY);
+        self.retry_at = Some(Instant::now() + self.delay);
+        self.delay
+    }
+
+    fn succeeded(&mut self) {
+        self.delay = Duration::ZERO;
+        self.retry_at = None;
+    }
+}
+
+struct Worker {
+    services: Vec<(Box<dyn Service>, Backoff)>,
+    pending: PendingScrobbles,
+}
+
+impl Worker {
+    fn new(services: Vec<Box<dyn Service>>, pending: PendingScrobbles) -> Worker {
+        let services = services
+            .into_iter()
+            .map(|service| (service, Backoff::default()))
+            .collect();
+        Worker { services, pending }
+    }
+
+    fn run(mut self, rx: Receiver<ScrobbleRequest>) {
+        loop {
+            // listens saved by the last session go out first
+            self.flush();
+            let request = match self.next_retry() {
+                Some(at) => match rx.recv_timeout(at.saturating_duration_since(Instant::now())) {
+                    Ok(request) => Some(request),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                },
+                None => match rx.recv() {
+                    Ok(request) => Some(request),
+                    Err(_) => return,
+                },
+            };
+            match request {
+                Some(ScrobbleRequest::NowPlaying(song)) => self.now_playing(&song),
+                Some(ScrobbleRequest::Listen(play)) => {
+                    let names = self.services.iter().map(|(service, _)| service.name());
+                    self.pending.add(names, play);
+                    save(&self.pending);
+                }
+                None => (),
+            }
+        }
+    }
+
+    // now playing isn't worth retrying, the song will have changed by then
+    fn now_playing(&self, song: &Song) {
+        for (service, backoff) in self.services.iter() {
+            if backoff.waiting() {
+                continue;
+            }
+            if let Err(err) = service.now_playing(song) {
+                info!("{}: could not send now playing: {}", service.name(), err);
+            }
+        }
+    }
+
+    // send the pending listens of every service that isn't waiting to retry
+    fn flush(&mut self) {
+        for (service, backoff) in self.services.iter_mut() {
+            if backoff.waiting() {
+                continue;
+            }
+            loop {
+                let batch = self.pending.batch(service.name(), service.batch_size());
+                if batch.is_empty() {
+                    break;
+                }
+                match service.submit(&batch) {
+                    Ok(()) => backoff.succeeded(),
+                    Err(err) if err.is_permanent() => {
+                        error!(
+                            "{}: dropping {} listens: {}",
+                            service.name(),
+                            batch.len(),
+                            err
+                        )
+                    }
+                    Err(err) => {
+                        let delay = backoff.failed();
+                        warn!("{}: {}, retrying in {:?}", service.name(), err, delay);
+                        break;
+                    }
+                }
+                self.pending.remove(service.name(), batch.len());
+                save(&self.pending);
+            }
+        }
+    }
+
+    // when the first service waiting with pending listens can be tried again
+    fn next_retry(&self) -> Option<Instant> {
+        self.services
+            .iter()
+            .filter(|(service, _)| self.pending.has(service.name()))
+            .filter_map(|(_, backoff)| backoff.retry_at)
+            .min()
+    }
+}
+
+fn save(pending: &PendingScrobbles) {
+    if let Err(err) = pending.save() {
+        error!("Could not save the pending scrobbles: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    // answers every submit with `error`, counting the listens it was sent
+    struct Stub {
+        error: Option<fn() -> ScrobbleError>,
+        submitted: Arc<Mutex<usize>>,
+    }
+
+    impl Service for Stub {
+        fn name(&self) -> &'static str {
+            "stub"
+        }
+
+        fn batch_size(&self) -> usize {
+            2
+        }
+
+        fn now_playing(&self, _song: &Song) -> Result<(), ScrobbleError> {
+            Ok(())
+        }
+
+        fn submit(&self, plays: &[Play]) -> Result<(), ScrobbleError> {
+            *self.submitted.lock().unwrap() += plays.len();
+            match self.error {
+                Some(error) => Err(error()),
+                None => Ok(()),
+            }
+        }
+    }
+
+    fn play(title: &str) -> Play {
+        Play {
+            path: format!("/music/{}.mp3", title),
+            title: title.to_string(),
+            artist: "Artist".to_string(),
+            album: "Album".to_string(),
+            album_artist: "Artist".to_string(),
+            duration_ms: 180_000,
+            time: 1_700_000_000,
+            listened_ms: 180_000,
+            skipped: false,
+        }
+    }
+
+    fn pending_file(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "splay-scrobbles-{}-{}.toml",
+            std::process::id(),
+            name
+        ))
+    }
+
+    // a worker for a stub with three pending listens, saved to a file named after the test
+    fn worker(name: &str, error: Option<fn() -> ScrobbleError>) -> (Worker, Arc<Mutex<usize>>) {
+        let _ = std::fs::remove_file(pending_file(name));
+        let mut pending = PendingScrobbles::load_from(pending_file(name));
+        for title in ["one", "two", "three"] {
+            pending.add(["stub"].into_iter(), play(title));
+        }
+        let submitted = Arc::new(Mutex::new(0));
+        let stub = Stub {
+            error,
+            submitted: submitted.clone(),
+        };
+        (Worker::new(vec![Box::new(stub)], pending), submitted)
+    }
+
+    #[test]
+    fn unreachable_service_keeps_listens() {
+        let (mut worker, submitted) = worker(
+            "unreachable",
+            Some(|| ScrobbleError::Unreachable("connection refused".to_string())),
+        );
+        worker.flush();
+        assert_eq!(*submitted.lock().unwrap(), 2);
+        assert_eq!(worker.pending.batch("stub", 10).len(), 3);
+        assert!(worker.services[0].1.waiting());
+        assert!(worker.next_retry().is_some());
+
+        // still waiting, nothing is sent
+        worker.flush();
+        assert_eq!(*submitted.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn permanent_error_drops_listens() {
+        let (mut worker, submitted) = worker(
+            "permanent",
+            Some(|| ScrobbleError::Rejected {
+                status: 400,
+                message: "invalid listen".to_string(),
+                permanent: true,
+            }),
+        );
+        worker.flush();
+        assert_eq!(*submitted.lock().unwrap(), 3);
+        assert!(!worker.pending.has("stub"));
+        assert!(!worker.services[0].1.waiting());
+        let saved = PendingScrobbles::load_from(pending_file("permanent"));
+        let _ = std::fs::remove_file(pending_file("permanent"));
+        assert!(!saved.has("stub"));
+    }
+
+    #[test]
+    fn sent_listens_are_saved_as_done() {
+        let (mut worker, submitted) = worker("sent", None);
+        worker.flush();
+        assert_eq!(*submitted.lock().unwrap(), 3);
+        assert!(!worker.pending.has("stub"));
+        assert!(worker.next_retry().is_none());
+        let _ = std::fs::remove_file(pending_file("sent"));
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::library::history::Play;
+use crate::library::playlist;
+
+#[derive(Serialize, Deserialize)]
+struct Pending {
+    // name of the service it still has to be sent to
+    service: String,
+    play: Play,
+}
+
+// Listens that weren't sent yet, oldest first, kept in `scrobbles.toml` in the data directory
+#[derive(Default, Serialize, Deserialize)]
+pub struct PendingScrobbles {
+    #[serde(default)]
+    pending: Vec<Pending>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl PendingScrobbles {
+    pub fn load() -> PendingScrobbles {
+        PendingScrobbles::load_from(pending_path())
+    }
+
+    // the listens saved to `path`, which they're saved to again
+    pub fn load_from(path: PathBuf) -> PendingScrobbles {
+        let mut pending = match fs::read_to_string(&path) {
+            Ok(text) => match toml::from_str(&text) {
+                Ok(pending) => pending,
+                Err(err) => {
+                    error!("Could not parse {}: {}", path.display(), err);
+                    PendingScrobbles::default()
+                }
+            },
+            Err(_) => PendingScrobbles::default(),
+        };
+        pending.path = path;
+        pending
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn add<'a>(&mut self, services: impl Iterator<Item = &'a str>, play: Play) {
+        for service in services {
+            self.pending.push(Pending {
+                service: service.to_string(),
+                play: play.clone(),
+            });
+        }
+    }
+
+    pub fn has(&self, service: &str) -> bool {
+        self.pending
+            .iter()
+            .any(|pending| pending.service == service)
+    }
+
+    // the first `count` listens to send to `service`
+    pub fn batch(&self, service: &str, count: usize) -> Vec<Play> {
+        self.pending
+            .iter()
+            .filter(|pending| pending.service == service)
+            .take(count)
+            .map(|pending| pending.play.clone())
+            .collect()
+    }
+
+    // drop the first `count` listens of `service` once they were sent
+    pub fn remove(&mut self, service: &str, mut count: usize) {
+        self.pending.retain(|pending| {
+            if count > 0 && pending.service == service {
+                count -= 1;
+                return false;
+            }
+            true
+        });
+    }
+}
+
+fn pending_path() -> PathBuf {
+    playlist::data_dir().join("scrobbles.toml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(title: &str) -> Play {
+        Play {
+            path: format!("/music/{}.mp3", title),
+            title: title.to_string(),
+            artist: "Artist".to_string(),
+            album: "Album".to_string(),
+            album_artist: "Artist".to_string(),
+            duration_ms: 180_000,
+            time: 1_700_000_000,
+            listened_ms: 180_000,
+            skipped: false,
+        }
+    }
+
+    fn titles(pending: &PendingScrobbles, service: &str) -> Vec<String> {
+        pending
+            .batch(service, usize::MAX)
+            .into_iter()
+            .map(|play| play.title)
+            .collect()
+    }
+
+    #[test]
+    fn remove_drops_the_first_listens_of_a_service() {
+        let mut pending = PendingScrobbles::default();
+        for title in ["one", "two", "three"] {
+            pending.add(["a", "b"].into_iter(), play(title));
+        }
+        pending.add(["a"].into_iter(), play("four"));
+
+        pending.remove("a", 2);
+        assert_eq!(titles(&pending, "a"), ["three", "four"]);
+        assert_eq!(titles(&pending, "b"), ["one", "two", "three"]);
+
+        pending.remove("b", 5);
+        assert!(!pending.has("b"));
+        assert_eq!(titles(&pending, "a"), ["three", "four"]);
+    }
+
+    #[test]
+    fn saved_listens_load_again() {
+        let path = std::env::temp_dir().join(format!("splay-pending-{}.toml", std::process::id()));
+        let mut pending = PendingScrobbles::load_from(path.clone());
+        pending.add(["a"].into_iter(), play("one"));
+        pending.save().unwrap();
+        let loaded = PendingScrobbles::load_from(path.clone());
+        let _ = fs::remove_file(path);
+        assert_eq!(titles(&loaded, "a"), ["one"]);
+    }
+}
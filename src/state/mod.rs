@@ -1,42 +1,102 @@
 use std::time::Duration;
 
 use crate::{
-    library::{song::Song, Library},
+    library::{history::History, playlist::Playlists, report::ImportReport, song::Song, Library},
+    queue::SongQueue,
+    scrobble::Scrobbler,
     utils::constants::PlayerStates,
 };
 
 pub struct AppState {
     pub library: Library,
+    pub playlists: Playlists,
+    pub history: History,
+    // None when scrobbling is off
+    pub scrobbler: Option<Scrobbler>,
+    pub queue: SongQueue,
     pub ui: UIState,
     pub player: PlayerState,
     pub search: SearchState,
+    pub import_report: ImportReport,
 }
 
 impl Default for AppState {
     fn default() -> AppState {
         AppState {
             library: Library::default(),
+            playlists: Playlists::default(),
+            history: History::default(),
+            scrobbler: None,
+            queue: SongQueue::new(),
             ui: UIState::default(),
             player: PlayerState::default(),
             search: SearchState::default(),
+            import_report: ImportReport::default(),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tab {
+    Songs,
+    Browse,
+    Playlists,
+    Queue,
+    Lyrics,
+    Stats,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 6] = [
+        Tab::Songs,
+        Tab::Browse,
+        Tab::Playlists,
+        Tab::Queue,
+        Tab::Lyrics,
+        Tab::Stats,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Tab::Songs => "Songs",
+            Tab::Browse => "Browse",
+            Tab::Playlists => "Playlists",
+            Tab::Queue => "Queue",
+            Tab::Lyrics => "Lyrics",
+            Tab::Stats => "Stats",
+        }
+    }
+
+    pub fn next(&self) -> Tab {
+        let idx = Tab::ALL.iter().position(|tab| tab == self).unwrap_or(0);
+        Tab::ALL[(idx + 1) % Tab::ALL.len()]
+    }
+}
+
 pub struct UIState {
-    curr_tab: u8,
+    pub curr_tab: Tab,
     selected_pane: u8,
     selected_row: u8,
     pub selected_song: Option<Song>,
+    pub show_import_report: bool,
+    pub import_report_scroll: u16,
+    // scroll position of unsynchronized lyrics
+    pub lyrics_scroll: u16,
+    // keyboard input goes to the open text field (tag editor, playlist name)
+    pub text_input: bool,
 }
 
 impl Default for UIState {
     fn default() -> Self {
         Self {
-            curr_tab: 0,
+            curr_tab: Tab::Songs,
             selected_pane: 0,
             selected_row: 0,
             selected_song: None,
+            show_import_report: false,
+            import_report_scroll: 0,
+            lyrics_scroll: 0,
+            text_input: false,
         }
     }
 }
@@ -44,6 +104,8 @@ impl Default for UIState {
 pub struct PlayerState {
     pub curr_state: PlayerStates,
     pub progress: Duration,
+    // time the current song has been played for, to tell if it counts as played
+    pub listened: Duration,
     pub curr_song: Option<Song>,
 }
 
@@ -52,6 +114,7 @@ impl Default for PlayerState {
         Self {
             curr_state: PlayerStates::STOPPED,
             progress: Duration::ZERO,
+            listened: Duration::ZERO,
             curr_song: None,
         }
     }
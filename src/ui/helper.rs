@@ -1,4 +1,6 @@
 use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::Style;
+use tui::text::Span;
 
 pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
@@ -25,3 +27,29 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         )
         .split(popup_layout[1])[1]
 }
+
+// `text` with the chars at the `matches` indices drawn in `style`
+pub fn highlight(text: &str, matches: &[usize], style: Style) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut current_matched = false;
+    for (idx, ch) in text.chars().enumerate() {
+        let matched = matches.binary_search(&idx).is_ok();
+        if matched != current_matched && !current.is_empty() {
+            spans.push(styled(std::mem::take(&mut current), current_matched, style));
+        }
+        current_matched = matched;
+        current.push(ch);
+    }
+    if !current.is_empty() {
+        spans.push(styled(current, current_matched, style));
+    }
+    spans
+}
+
+fn styled(text: String, matched: bool, style: Style) -> Span<'static> {
+    match matched {
+        true => Span::styled(text, style),
+        false => Span::raw(text),
+    }
+}
@@ -1,14 +1,20 @@
 pub mod helper;
 pub mod widgets;
 
+use crate::library::history::Period;
+use crate::library::lyrics::Lyrics;
+use crate::library::playlist::Playlist;
+use crate::library::query::Query;
 use crate::library::song::Song;
 use crate::library::tag;
 use crate::player::symphonia_player::SymphoniaPlayer;
 use crate::player::Player;
-use crate::state::AppState;
+use crate::state::{AppState, Tab};
 use crate::utils::constants::requests::{AppRequests, PlayerRequests, UIRequests::*};
 use crate::utils::constants::PlayerStates;
 use crate::{library::Library, utils::constants::requests::UIRequests};
+use rand::seq::SliceRandom;
+use std::path::PathBuf;
 use std::sync::{mpsc, Arc, Mutex};
 use std::{
     fmt::format,
@@ -19,7 +25,12 @@ use std::{
 use std::{thread, time};
 use tui::layout::Alignment;
 use tui::widgets::Wrap;
+use widgets::album_art::{AlbumArt, ArtMode};
+use widgets::browser::Browser;
+use widgets::playlists::{PlaylistPicker, PlaylistView, Prompt};
+use widgets::queue::QueueView;
 use widgets::stateful_list::StatefulList;
+use widgets::tag_editor::TagEditor;
 
 use crossterm::{
     cursor, event,
@@ -33,7 +44,7 @@ use tui::{
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs},
     Frame, Terminal,
 };
 
@@ -41,6 +52,8 @@ pub fn start<'a>(
     app_state: Arc<Mutex<AppState>>,
     rx: Receiver<UIRequests>,
     main_tx: Sender<AppRequests>,
+    art_mode: ArtMode,
+    saved_searches: Vec<(String, Query)>,
 ) {
     info!("Starting up UI...");
 
@@ -61,7 +74,9 @@ pub fn start<'a>(
 
     let mut songs = app_state.lock().unwrap().library.songs.to_owned();
     songs.sort_by(|a, b| a.title.cmp(&b.title));
-    let app = App::with_songs(app_state, songs);
+    let mut app = App::with_songs(app_state, songs);
+    app.album_art = AlbumArt::new(art_mode);
+    app.browser.set_searches(saved_searches);
     app.run(&mut terminal, rx, main_tx);
 
     info!("stopping now");
@@ -79,9 +94,61 @@ pub fn start<'a>(
     info!("Terminal cleaned successfully");
 }
 
+// char indices to highlight in the album and title of each search result
+type Highlights = Vec<(Vec<usize>, Vec<usize>)>;
+
+// Results of the search term, kept until the term or the library changes
+struct SearchResults {
+    term: String,
+    revision: u64,
+    songs: Vec<Song>,
+    highlights: Highlights,
+    error: Option<String>,
+}
+
+impl SearchResults {
+    fn new(term: &str, library: &Library) -> SearchResults {
+        let (hits, error) = match library.search(term) {
+            Ok(hits) => (hits, None),
+            Err(err) => (Vec::new(), Some(err.to_string())),
+        };
+        let (songs, highlights) = hits
+            .into_iter()
+            .map(|hit| (hit.song.clone(), (hit.album_matches, hit.title_matches)))
+            .unzip();
+        SearchResults {
+            term: term.to_string(),
+            revision: library.revision(),
+            songs,
+            highlights,
+            error,
+        }
+    }
+
+    fn is_current(&self, term: &str, library: &Library) -> bool {
+        self.term == term && self.revision == library.revision()
+    }
+}
+
 pub struct App {
     state: Arc<Mutex<AppState>>,
     song_list: StatefulList<Song>,
+    // songs listed in the Songs tab, the search results while searching
+    shown_songs: Vec<Song>,
+    browser: Browser,
+    playlists: PlaylistView,
+    // open while picking the playlist to add a song to
+    playlist_picker: Option<PlaylistPicker>,
+    queue: QueueView,
+    album_art: AlbumArt,
+    tag_editor: Option<TagEditor>,
+    lyrics: Option<Lyrics>,
+    // path of the song the lyrics were loaded for
+    lyrics_song: Option<String>,
+    stats_period: Period,
+    // library revision the song list and browser were last loaded from
+    library_revision: u64,
+    search_results: Option<SearchResults>,
 }
 
 impl App {
@@ -89,14 +156,92 @@ impl App {
         App {
             state,
             song_list: StatefulList::with_items(vec![]),
+            shown_songs: Vec::new(),
+            browser: Browser::new(),
+            playlists: PlaylistView::new(),
+            playlist_picker: None,
+            queue: QueueView::new(),
+            album_art: AlbumArt::new(ArtMode::Off),
+            tag_editor: None,
+            lyrics: None,
+            lyrics_song: None,
+            stats_period: Period::default(),
+            library_revision: 0,
+            search_results: None,
         }
     }
 
     pub fn with_songs(state: Arc<Mutex<AppState>>, songs: Vec<Song>) -> App {
+        let library_revision = state.lock().unwrap().library.revision();
+        let mut browser = Browser::new();
+        browser.refresh(&state.lock().unwrap().library);
+        let mut playlists = PlaylistView::new();
+        {
+            let state = state.lock().unwrap();
+            playlists.refresh(&state.playlists, &state.library);
+        }
         App {
             state,
             song_list: StatefulList::with_items(songs),
+            shown_songs: Vec::new(),
+            browser,
+            playlists,
+            playlist_picker: None,
+            queue: QueueView::new(),
+            album_art: AlbumArt::new(ArtMode::Off),
+            tag_editor: None,
+            lyrics: None,
+            lyrics_song: None,
+            stats_period: Period::default(),
+            library_revision,
+            search_results: None,
+        }
+    }
+
+    // reload the song list, browser and playlists if the library changed (e.g. after a rescan)
+    fn sync_library(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        if state.library.revision() == self.library_revision {
+            return;
+        }
+        self.library_revision = state.library.revision();
+        let mut songs = state.library.songs.to_owned();
+        songs.sort_by(|a, b| a.title.cmp(&b.title));
+        self.song_list.items = songs;
+        match self.song_list.state.selected() {
+            _ if self.song_list.items.is_empty() => self.song_list.unselect(),
+            Some(idx) if idx >= self.song_list.items.len() => {
+                self.song_list.state.select(Some(self.song_list.items.len() - 1))
+            }
+            None => self.song_list.state.select(Some(0)),
+            _ => (),
+        }
+        self.browser.refresh(&state.library);
+        state.playlists.update_smart(&state.library);
+        self.playlists.refresh(&state.playlists, &state.library);
+    }
+
+    // load the artwork of the current song, returns true if the terminal needs to be cleared
+    fn sync_artwork(&mut self) -> bool {
+        let state = self.state.lock().unwrap();
+        let artwork = match &state.player.curr_song {
+            Some(song) => state.library.song_artwork(song),
+            None => None,
+        };
+        self.album_art.set_artwork(artwork)
+    }
+
+    // load the lyrics of the current song when it changes
+    fn sync_lyrics(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        let curr_song = state.player.curr_song.as_ref();
+        if curr_song.map(|song| &song.path) == self.lyrics_song.as_ref() {
+            return;
         }
+        self.lyrics = curr_song.and_then(Lyrics::load);
+        self.lyrics_song = curr_song.map(|song| song.path.to_owned());
+        state.ui.lyrics_scroll = 0;
     }
 
     #[warn(unreachable_patterns)]
@@ -117,13 +262,73 @@ impl App {
             let timeout = tick_rate
                 .checked_sub(last_tick.elapsed())
                 .unwrap_or_else(|| Duration::from_secs(0));
+            self.sync_library();
+            self.sync_lyrics();
+            if self.sync_artwork() {
+                let _ = terminal.clear();
+            }
             terminal.draw(|f| self.get_ui(f, &main_tx)).unwrap();
+            if let Err(err) = self.album_art.flush(terminal.backend_mut()) {
+                error!("Could not draw album art: {}", err);
+            }
             match rx.recv_timeout(timeout) {
                 Ok(request) => match request {
                     Up => self.on_up(),
                     Down => self.on_down(),
-                    Enter => self.on_enter(),
+                    Enter => self.on_enter(&main_tx),
+                    NextTab => self.next_tab(),
                     ShowSearch => self.state.lock().unwrap().search.searching = true,
+                    ShowImportReport => self.toggle_import_report(),
+                    EditTags => self.open_tag_editor(false),
+                    EditAlbumTags => self.open_tag_editor(true),
+                    EditInput(ch) => {
+                        if let Some(editor) = self.tag_editor.as_mut() {
+                            editor.push(ch)
+                        } else if let Some((prompt, name)) = self.playlists.prompt.as_mut() {
+                            if !matches!(prompt, Prompt::Delete) {
+                                name.push(ch)
+                            }
+                        }
+                    }
+                    Backspace => {
+                        if let Some(editor) = self.tag_editor.as_mut() {
+                            editor.backspace()
+                        } else if let Some((prompt, name)) = self.playlists.prompt.as_mut() {
+                            if !matches!(prompt, Prompt::Delete) {
+                                name.pop();
+                            }
+                        }
+                    }
+                    AddToPlaylist => self.open_playlist_picker(),
+                    NewPlaylist => self.open_playlist_prompt(Prompt::Create),
+                    NewSmartPlaylist => self.open_playlist_prompt(Prompt::SmartName),
+                    EditSmartRules => self.open_playlist_prompt(Prompt::SmartRules(None)),
+                    RenamePlaylist => self.open_playlist_prompt(Prompt::Rename),
+                    ImportPlaylist => self.open_playlist_prompt(Prompt::Import),
+                    ExportPlaylist => self.open_playlist_prompt(Prompt::Export),
+                    PlayPlaylist => self.play_playlist(false, &main_tx),
+                    ShufflePlaylist => self.play_playlist(true, &main_tx),
+                    CycleShuffle => {
+                        let queue = &mut self.state.lock().unwrap().queue;
+                        queue.set_shuffle(queue.shuffle().next());
+                    }
+                    CycleRepeat => {
+                        let queue = &mut self.state.lock().unwrap().queue;
+                        queue.set_repeat(queue.repeat().next());
+                    }
+                    CycleStatsPeriod => self.stats_period = self.stats_period.next(),
+                    SetRating(rating) => self.rate_selected_song(Some(rating)),
+                    ToggleLoved => self.rate_selected_song(None),
+                    ToggleRadio => {
+                        let queue = &mut self.state.lock().unwrap().queue;
+                        queue.set_radio(!queue.radio());
+                    }
+                    PlayNext => self.queue_selected_song(true),
+                    AddToQueue => self.queue_selected_song(false),
+                    ClearQueue => self.clear_queue(),
+                    Delete => self.remove_from_queue_or_playlists(),
+                    MoveUp => self.move_in_queue_or_playlist(true),
+                    MoveDown => self.move_in_queue_or_playlist(false),
                     SearchInput(ch) => self.state.lock().unwrap().search.term.push(ch),
                     GoBack => self.go_back(),
                     Quit => return,
@@ -146,42 +351,509 @@ impl App {
     }
 
     fn on_up(&mut self) {
-        match self.song_list.state.selected() {
-            Some(idx) => if idx == 0 {return} else { () },
-            None => return,
+        if let Some(editor) = self.tag_editor.as_mut() {
+            editor.up();
+            return;
         }
-        self.song_list.previous();
-        match self.song_list.items.get(self.song_list.state.selected().unwrap()) {
-            Some(song) => {
-                self.state.lock().unwrap().ui.selected_song = Some(song.clone());
-            },
-            None => ()
+        if let Some(picker) = self.playlist_picker.as_mut() {
+            if picker.list.len() != 0 {
+                picker.list.previous();
+            }
+            return;
         }
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.ui.show_import_report {
+                state.ui.import_report_scroll = state.ui.import_report_scroll.saturating_sub(1);
+                return;
+            }
+            if state.ui.curr_tab == Tab::Lyrics {
+                state.ui.lyrics_scroll = state.ui.lyrics_scroll.saturating_sub(1);
+                return;
+            }
+            if state.ui.curr_tab == Tab::Stats {
+                return;
+            }
+            if state.ui.curr_tab == Tab::Browse {
+                if self.browser.list.len() != 0 {
+                    self.browser.list.previous();
+                }
+                state.ui.selected_song = self.browser.selected_song().cloned();
+                return;
+            }
+            if state.ui.curr_tab == Tab::Playlists {
+                if self.playlists.list.len() != 0 {
+                    self.playlists.list.previous();
+                }
+                state.ui.selected_song = self
+                    .playlists
+                    .selected_song(&state.playlists, &state.library)
+                    .cloned();
+                return;
+            }
+            if state.ui.curr_tab == Tab::Queue {
+                self.queue.up(&state.queue);
+                state.ui.selected_song = self.queue.selected_song(&state.queue).cloned();
+                return;
+            }
+        }
+        // the rows are the search results while searching
+        let last = self.shown_songs.len().saturating_sub(1);
+        let prev = match self.song_list.state.selected() {
+            Some(idx) if idx > 0 => (idx - 1).min(last),
+            _ => return,
+        };
+        self.song_list.state.select(Some(prev));
+        self.state.lock().unwrap().ui.selected_song = self.shown_songs.get(prev).cloned();
     }
 
     fn on_down(&mut self) {
-        let length = self.song_list.len();
-        match self.song_list.state.selected() {
-            Some(idx) => if idx == length {return} else { () },
-            None => (),
-        }
-        // panic!("yo");
-        self.song_list.next();
-        match self.song_list.items.get(self.song_list.state.selected().unwrap()) {
-            Some(song) => {
-                self.state.lock().unwrap().ui.selected_song = Some(song.clone());
+        if let Some(editor) = self.tag_editor.as_mut() {
+            editor.down();
+            return;
+        }
+        if let Some(picker) = self.playlist_picker.as_mut() {
+            if picker.list.len() != 0 {
+                picker.list.next();
+            }
+            return;
+        }
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.ui.show_import_report {
+                let max_scroll = state.import_report.failed.len().saturating_sub(1) as u16;
+                if state.ui.import_report_scroll < max_scroll {
+                    state.ui.import_report_scroll += 1;
+                }
+                return;
+            }
+            if state.ui.curr_tab == Tab::Stats {
+                return;
+            }
+            if state.ui.curr_tab == Tab::Lyrics {
+                let max_scroll = match &self.lyrics {
+                    Some(lyrics) => lyrics.lines.len().saturating_sub(1) as u16,
+                    None => 0,
+                };
+                if state.ui.lyrics_scroll < max_scroll {
+                    state.ui.lyrics_scroll += 1;
+                }
+                return;
+            }
+            if state.ui.curr_tab == Tab::Browse {
+                if self.browser.list.len() != 0 {
+                    self.browser.list.next();
+                }
+                state.ui.selected_song = self.browser.selected_song().cloned();
+                return;
+            }
+            if state.ui.curr_tab == Tab::Playlists {
+                if self.playlists.list.len() != 0 {
+                    self.playlists.list.next();
+                }
+                state.ui.selected_song = self
+                    .playlists
+                    .selected_song(&state.playlists, &state.library)
+                    .cloned();
+                return;
+            }
+            if state.ui.curr_tab == Tab::Queue {
+                self.queue.down(&state.queue);
+                state.ui.selected_song = self.queue.selected_song(&state.queue).cloned();
+                return;
+            }
+        }
+        let length = self.shown_songs.len();
+        let next = match self.song_list.state.selected() {
+            Some(idx) if idx + 1 >= length => return,
+            Some(idx) => idx + 1,
+            None if length == 0 => return,
+            None => 0,
+        };
+        self.song_list.state.select(Some(next));
+        self.state.lock().unwrap().ui.selected_song = self.shown_songs.get(next).cloned();
+    }
+
+    fn on_enter(&mut self, main_tx: &Sender<AppRequests>) {
+        if self.tag_editor.is_some() {
+            self.save_tag_editor();
+            return;
+        }
+        if self.playlist_picker.is_some() {
+            self.add_to_picked_playlist();
+            return;
+        }
+        if self.playlists.prompt.is_some() {
+            self.submit_playlist_prompt();
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.ui.curr_tab == Tab::Lyrics || state.ui.curr_tab == Tab::Stats {
+            return;
+        }
+        if state.ui.curr_tab == Tab::Queue {
+            // play the selected song now, skipping the upcoming songs before it
+            if let Some(pos) = self.queue.selected {
+                if state.queue.jump(pos) {
+                    let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::Next));
+                }
+            }
+            return;
+        }
+        if state.ui.curr_tab == Tab::Playlists {
+            if !self.playlists.open(&state.playlists, &state.library) {
+                // play the open playlist from the selected track
+                drop(state);
+                self.play_playlist(false, main_tx);
+            } else {
+                state.ui.selected_song = self
+                    .playlists
+                    .selected_song(&state.playlists, &state.library)
+                    .cloned();
+            }
+            return;
+        }
+        if state.ui.curr_tab == Tab::Browse {
+            if self.browser.enter(&state.library).is_none() {
+                state.ui.selected_song = self.browser.selected_song().cloned();
+                return;
+            }
+            // the rest of the album, artist, genre, ... tracks follow the selected one
+            if let Some(idx) = self.browser.list.state.selected() {
+                play_from(&mut state, self.browser.songs().to_vec(), idx, main_tx);
+            }
+            return;
+        }
+        // the rest of the song list, or of the search results while searching
+        if let Some(idx) = self.song_list.state.selected() {
+            play_from(&mut state, self.shown_songs.clone(), idx, main_tx);
+        }
+    }
+
+    fn next_tab(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.ui.curr_tab = state.ui.curr_tab.next();
+        state.ui.selected_song = match state.ui.curr_tab {
+            Tab::Songs => match self.song_list.state.selected() {
+                Some(idx) => self.shown_songs.get(idx).cloned(),
+                None => None,
             },
-            None => ()
+            Tab::Browse => self.browser.selected_song().cloned(),
+            Tab::Playlists => self
+                .playlists
+                .selected_song(&state.playlists, &state.library)
+                .cloned(),
+            Tab::Queue => self.queue.selected_song(&state.queue).cloned(),
+            // keep the selection of the previous tab
+            Tab::Lyrics | Tab::Stats => return,
+        };
+    }
+
+    fn open_tag_editor(&mut self, album: bool) {
+        let mut state = self.state.lock().unwrap();
+        let song = match &state.ui.selected_song {
+            Some(song) => song,
+            None => return,
+        };
+        self.tag_editor = Some(match album {
+            true => TagEditor::for_album(
+                &state
+                    .library
+                    .album_tracks(&song.album_artist, &song.album_title),
+            ),
+            false => TagEditor::for_song(song),
+        });
+        state.ui.text_input = true;
+    }
+
+    // write the changes to the files, the editor stays open showing the error if it fails
+    fn save_tag_editor(&mut self) {
+        let editor = match self.tag_editor.as_mut() {
+            Some(editor) => editor,
+            None => return,
+        };
+        let changes = editor.changes();
+        let mut state = self.state.lock().unwrap();
+        if !changes.is_empty() {
+            if let Err(err) = state.library.edit_tags(&editor.paths, &changes) {
+                error!("Could not save tags: {}", err);
+                editor.error = Some(err.to_string());
+                return;
+            }
         }
+        self.tag_editor = None;
+        state.ui.text_input = false;
     }
 
-    fn on_enter(&mut self) {}
+    fn toggle_import_report(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.ui.show_import_report = !state.ui.show_import_report;
+        state.ui.import_report_scroll = 0;
+    }
 
     fn go_back(&mut self) {
+        if self.tag_editor.take().is_some() {
+            self.state.lock().unwrap().ui.text_input = false;
+            return;
+        }
+        if self.playlist_picker.take().is_some() {
+            return;
+        }
+        if self.playlists.prompt.take().is_some() {
+            self.state.lock().unwrap().ui.text_input = false;
+            return;
+        }
+        if self.state.lock().unwrap().ui.show_import_report {
+            self.state.lock().unwrap().ui.show_import_report = false;
+            return;
+        }
         if self.state.lock().unwrap().search.searching {
             self.state.lock().unwrap().search.searching = false;
             self.state.lock().unwrap().search.term.clear();
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.ui.curr_tab == Tab::Browse {
+            self.browser.back(&state.library);
+            state.ui.selected_song = self.browser.selected_song().cloned();
+        }
+        if state.ui.curr_tab == Tab::Playlists {
+            self.playlists.error = None;
+            self.playlists.back(&state.playlists, &state.library);
+            state.ui.selected_song = None;
+        }
+    }
+
+    fn open_playlist_picker(&mut self) {
+        let state = self.state.lock().unwrap();
+        if let Some(song) = &state.ui.selected_song {
+            self.playlist_picker = Some(PlaylistPicker::new(song.clone(), &state.playlists));
+        }
+    }
+
+    fn add_to_picked_playlist(&mut self) {
+        let picker = match self.playlist_picker.take() {
+            Some(picker) => picker,
+            None => return,
+        };
+        let idx = match picker.playlist() {
+            Some(idx) => idx,
+            None => return,
+        };
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        if let Err(err) = state.playlists.add_song(idx, &picker.song) {
+            error!("{}", err);
+        }
+        self.playlists.refresh(&state.playlists, &state.library);
+    }
+
+    // ask for a playlist name or smart playlist rules, or the file to import from or export the
+    // selected playlist to
+    fn open_playlist_prompt(&mut self, prompt: Prompt) {
+        let mut state = self.state.lock().unwrap();
+        if state.ui.curr_tab != Tab::Playlists {
+            return;
+        }
+        let selected = self.playlists.playlist().and_then(|idx| state.playlists.get(idx));
+        let text = match (&prompt, selected) {
+            (Prompt::Create | Prompt::SmartName | Prompt::Import, _) => String::new(),
+            (Prompt::Rename | Prompt::Delete, Some(playlist)) => playlist.name.clone(),
+            (Prompt::SmartRules(None), Some(playlist)) => match &playlist.rules {
+                Some(rules) => rules.clone(),
+                None => return,
+            },
+            (Prompt::Export, Some(playlist)) => dirs::home_dir()
+                .unwrap_or_default()
+                .join(format!("{}.m3u8", playlist.name))
+                .to_string_lossy()
+                .into_owned(),
+            _ => return,
+        };
+        self.playlists.prompt = Some((prompt, text));
+        state.ui.text_input = true;
+    }
+
+    // the prompt stays open showing the error if it fails
+    fn submit_playlist_prompt(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let selected = self.playlists.playlist();
+        let result = match (&self.playlists.prompt, selected) {
+            (Some((Prompt::Create, name)), _) => state.playlists.create(name),
+            // the rules are asked for once the name is known to be free
+            (Some((Prompt::SmartName, name)), _) => match state.playlists.check_name(name) {
+                Ok(name) => {
+                    self.playlists.prompt = Some((Prompt::SmartRules(Some(name)), String::new()));
+                    self.playlists.error = None;
+                    return;
+                }
+                Err(err) => Err(err),
+            },
+            (Some((Prompt::SmartRules(Some(name)), rules)), _) => {
+                state.playlists.create_smart(name, rules, &state.library)
+            }
+            (Some((Prompt::SmartRules(None), rules)), Some(idx)) => {
+                state.playlists.set_rules(idx, rules, &state.library)
+            }
+            (Some((Prompt::Rename, name)), Some(idx)) => state.playlists.rename(idx, name),
+            (Some((Prompt::Delete, _)), Some(idx)) => state.playlists.delete(idx),
+            (Some((Prompt::Import, path)), _) => {
+                Playlist::import(&expand_home(path), &state.library)
+                    .and_then(|playlist| state.playlists.add(playlist))
+            }
+            (Some((Prompt::Export, path)), Some(idx)) => match state.playlists.get(idx) {
+                Some(playlist) => playlist.export(&expand_home(path), &state.library),
+                None => Ok(()),
+            },
+            _ => return,
+        };
+        match result {
+            Ok(()) => {
+                self.playlists.prompt = None;
+                self.playlists.error = None;
+                state.ui.text_input = false;
+            }
+            Err(err) => self.playlists.error = Some(err.to_string()),
         }
+        self.playlists.refresh(&state.playlists, &state.library);
+    }
+
+    // remove the selected track from the open playlist, or ask to delete the selected playlist
+    fn delete_from_playlists(&mut self) {
+        if self.playlists.track().is_none() {
+            self.open_playlist_prompt(Prompt::Delete);
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        if state.ui.curr_tab != Tab::Playlists {
+            return;
+        }
+        let idx = match self.playlists.playlist() {
+            Some(idx) => idx,
+            None => return,
+        };
+        let result = match self.playlists.track() {
+            Some(pos) => state.playlists.remove_song(idx, pos),
+            None => return,
+        };
+        self.playlists.error = result.err().map(|err| err.to_string());
+        self.playlists.refresh(&state.playlists, &state.library);
+        state.ui.selected_song = self
+            .playlists
+            .selected_song(&state.playlists, &state.library)
+            .cloned();
+    }
+
+    fn move_in_playlist(&mut self, up: bool) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        if state.ui.curr_tab != Tab::Playlists {
+            return;
+        }
+        let (idx, pos) = match (self.playlists.playlist(), self.playlists.track()) {
+            (Some(idx), Some(pos)) => (idx, pos),
+            _ => return,
+        };
+        let to = match up {
+            true if pos > 0 => pos - 1,
+            false if pos + 1 < self.playlists.list.len() => pos + 1,
+            _ => return,
+        };
+        self.playlists.error = state.playlists.move_song(idx, pos, to).err().map(|e| e.to_string());
+        self.playlists.list.state.select(Some(to));
+        self.playlists.refresh(&state.playlists, &state.library);
+    }
+
+    // add the selected song to the songs played next, before (`first`) or after the others
+    fn queue_selected_song(&mut self, first: bool) {
+        let mut state = self.state.lock().unwrap();
+        let song = match state.ui.selected_song.clone() {
+            Some(song) => song,
+            None => return,
+        };
+        match first {
+            true => state.queue.add_first_immediate(song),
+            false => state.queue.add_last_immediate(song),
+        }
+    }
+
+    // Sets the rating of the selected song, or the playing one if nothing is selected, or
+    // toggles whether it's loved when `rating` is None
+    fn rate_selected_song(&mut self, rating: Option<u8>) {
+        let mut state = self.state.lock().unwrap();
+        let path = match state.ui.selected_song.as_ref().or(state.player.curr_song.as_ref()) {
+            Some(song) => song.path.to_owned(),
+            None => return,
+        };
+        let result = match rating {
+            Some(rating) => state.library.set_rating(&path, rating),
+            None => {
+                let loved = state.library.song(&path).is_some_and(|song| song.loved);
+                state.library.set_loved(&path, !loved)
+            }
+        };
+        if let Err(err) = result {
+            error!("Could not rate {}: {}", path, err);
+        }
+    }
+
+    fn clear_queue(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        if state.ui.curr_tab == Tab::Queue {
+            state.queue.clear();
+            self.queue.clamp(&state.queue);
+        }
+    }
+
+    fn remove_from_queue_or_playlists(&mut self) {
+        if self.state.lock().unwrap().ui.curr_tab != Tab::Queue {
+            self.delete_from_playlists();
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if let Some(pos) = self.queue.selected {
+            state.queue.remove(pos);
+            self.queue.clamp(&state.queue);
+            state.ui.selected_song = self.queue.selected_song(&state.queue).cloned();
+        }
+    }
+
+    fn move_in_queue_or_playlist(&mut self, up: bool) {
+        if self.state.lock().unwrap().ui.curr_tab != Tab::Queue {
+            self.move_in_playlist(up);
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if let Some(pos) = self.queue.selected.and_then(|pos| state.queue.move_song(pos, up)) {
+            self.queue.selected = Some(pos);
+        }
+    }
+
+    // Replace the queue with the selected playlist, from the selected track when one is open
+    fn play_playlist(&mut self, shuffle: bool, main_tx: &Sender<AppRequests>) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        if state.ui.curr_tab != Tab::Playlists {
+            return;
+        }
+        let playlist = match self.playlists.playlist().and_then(|idx| state.playlists.get(idx)) {
+            Some(playlist) => playlist,
+            None => return,
+        };
+        let start = match shuffle {
+            true => 0,
+            false => self.playlists.track().unwrap_or(0),
+        };
+        let mut songs: Vec<Song> = playlist.paths[start.min(playlist.paths.len())..]
+            .iter()
+            .filter_map(|path| state.library.song(path).cloned())
+            .collect();
+        if shuffle {
+            songs.shuffle(&mut rand::thread_rng());
+        }
+        play_from(state, songs, 0, main_tx);
     }
 
     fn get_ui<B: Backend>(&mut self, frame: &mut Frame<B>, main_tx: &Sender<AppRequests>) {
@@ -225,34 +897,63 @@ impl App {
         //     x => info!("{:?}", x)
         // }
 
-        let mut filtered_songs: Vec<Song> = Vec::new();
-        if self.state.lock().unwrap().search.searching {
-            let search = Paragraph::new(self.state.lock().unwrap().search.term.to_owned())
-                .style(Style::default().fg(Color::White))
-                .alignment(Alignment::Left)
-                .wrap(Wrap { trim: false });
-            frame.render_widget(Clear, song_list_vert_chunks[0]);
-            frame.render_widget(search, song_list_vert_chunks[0]);
-
-            let search_term = &self.state.lock().unwrap().search.term.to_lowercase();
-            for song in self.song_list.items.iter() {
-                if song.title.to_lowercase().contains(search_term) {
-                    filtered_songs.push(song.clone());
+        let curr_tab = self.state.lock().unwrap().ui.curr_tab;
+        let titles = Tab::ALL.iter().map(|tab| Spans::from(tab.title())).collect();
+        let tabs = Tabs::new(titles)
+            .select(Tab::ALL.iter().position(|tab| *tab == curr_tab).unwrap_or(0))
+            .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+        frame.render_widget(tabs, song_list_vert_chunks[0]);
+
+        let (filtered_songs, highlights): (Vec<Song>, Highlights) =
+            if self.state.lock().unwrap().search.searching {
+                let state = self.state.lock().unwrap();
+                // searching the whole library every frame would hold up the other threads
+                let results = match self.search_results.take() {
+                    Some(results) if results.is_current(&state.search.term, &state.library) => {
+                        results
+                    }
+                    _ => SearchResults::new(&state.search.term, &state.library),
+                };
+                let mut line = vec![Span::raw(state.search.term.to_owned())];
+                if let Some(error) = &results.error {
+                    line.push(Span::styled(
+                        format!("  {}", error),
+                        Style::default().fg(Color::Red),
+                    ));
                 }
-            }
-        } else {
-            filtered_songs = self.song_list.items.clone();
-        }
+                let search = Paragraph::new(Spans::from(line))
+                    .style(Style::default().fg(Color::White))
+                    .alignment(Alignment::Left)
+                    .wrap(Wrap { trim: false });
+                frame.render_widget(Clear, song_list_vert_chunks[0]);
+                frame.render_widget(search, song_list_vert_chunks[0]);
 
+                let shown = (results.songs.clone(), results.highlights.clone());
+                self.search_results = Some(results);
+                shown
+            } else {
+                let items = self.song_list.items.clone();
+                let highlights = vec![(Vec::new(), Vec::new()); items.len()];
+                (items, highlights)
+            };
+
+        self.shown_songs = filtered_songs.clone();
         let filtered_stateful_list = StatefulList::with_items(filtered_songs);
 
+        let match_style = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
         let list: Vec<ListItem> = filtered_stateful_list
             .items
             .iter()
-            .map(|i| {
-                let mut album = i.album_title.clone();
-                album.truncate(16);
-                ListItem::new(vec![Spans::from(format!("{: <16} {}", album, i.title.clone()))])
+            .zip(highlights.iter())
+            .map(|(i, (album_matches, title_matches))| {
+                let album: String = i.album_title.chars().take(16).collect();
+                let padding = " ".repeat(17 - album.chars().count());
+                let mut spans = helper::highlight(&album, album_matches, match_style);
+                spans.push(Span::raw(padding));
+                spans.extend(helper::highlight(&i.title, title_matches, match_style));
+                ListItem::new(vec![Spans::from(spans)])
             })
             .collect();
 
@@ -265,7 +966,95 @@ impl App {
             )
             .highlight_symbol(">> ");
 
-        frame.render_stateful_widget(list, song_list_vert_chunks[1], &mut self.song_list.state);
-        widgets::curr_playing_bar::render(frame, vert_chunks[0], &(self.state.lock().unwrap()));
+        match curr_tab {
+            Tab::Songs => frame.render_stateful_widget(
+                list,
+                song_list_vert_chunks[1],
+                &mut self.song_list.state,
+            ),
+            Tab::Browse => {
+                widgets::browser::render(frame, song_list_vert_chunks[1], &mut self.browser)
+            }
+            Tab::Playlists => widgets::playlists::render(
+                frame,
+                song_list_vert_chunks[1],
+                &mut self.playlists,
+                &self.state.lock().unwrap().playlists,
+            ),
+            Tab::Queue => widgets::queue::render(
+                frame,
+                song_list_vert_chunks[1],
+                &mut self.queue,
+                &self.state.lock().unwrap().queue,
+            ),
+            Tab::Lyrics => {
+                let state = self.state.lock().unwrap();
+                widgets::lyrics::render(
+                    frame,
+                    song_list_vert_chunks[1],
+                    self.lyrics.as_ref(),
+                    state.player.progress,
+                    state.ui.lyrics_scroll,
+                )
+            }
+            Tab::Stats => widgets::stats::render(
+                frame,
+                song_list_vert_chunks[1],
+                &self.state.lock().unwrap().history,
+                self.stats_period,
+            ),
+        }
+        let art_width = self.album_art.width(vert_chunks[0].height);
+        let now_playing_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(art_width), Constraint::Min(0)].as_ref())
+            .split(vert_chunks[0]);
+        self.album_art.render(frame, now_playing_chunks[0]);
+        widgets::curr_playing_bar::render(
+            frame,
+            now_playing_chunks[1],
+            &(self.state.lock().unwrap()),
+        );
+
+        if let Some(picker) = self.playlist_picker.as_mut() {
+            widgets::playlists::render_picker(frame, picker);
+        }
+        if let Some(editor) = &self.tag_editor {
+            widgets::tag_editor::render(frame, editor);
+        }
+
+        let state = self.state.lock().unwrap();
+        if state.ui.show_import_report {
+            widgets::import_report::render(
+                frame,
+                &state.import_report,
+                state.ui.import_report_scroll,
+            );
+        }
+    }
+}
+
+// Plays `songs[idx]` and replaces the queue with the songs after it
+fn play_from(
+    state: &mut AppState,
+    mut songs: Vec<Song>,
+    idx: usize,
+    main_tx: &Sender<AppRequests>,
+) {
+    if idx >= songs.len() {
+        return;
+    }
+    let upcoming = songs.split_off(idx + 1);
+    state.ui.selected_song = songs.pop();
+    state.queue.clear();
+    state.queue.set_upcoming(upcoming);
+    let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::Start));
+}
+
+// paths typed in the UI may start with ~
+fn expand_home(path: &str) -> PathBuf {
+    match (path.trim().strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path.trim()),
     }
 }
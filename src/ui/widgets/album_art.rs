@@ -0,0 +1,307 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crossterm::{cursor, queue};
+use image::{imageops::FilterType, DynamicImage, RgbImage};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use tui::{buffer::Buffer, layout::Rect, style::Color, widgets::Clear, widgets::Widget, Frame};
+
+// Size of a terminal cell in pixels assumed when sizing sixel images, most terminals use
+// larger cells so the image ends up smaller than its area rather than overflowing it
+const SIXEL_CELL_SIZE: (u32, u32) = (8, 16);
+// kitty graphics payloads have to be sent in chunks of at most 4096 bytes
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+// `album_art` setting in the [ui] section of the config
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtMode {
+    #[default]
+    Auto,
+    HalfBlocks,
+    Sixel,
+    Kitty,
+    Off,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Protocol {
+    // two pixels per cell using "▀" with the top pixel as foreground and the bottom as background
+    HalfBlocks { truecolor: bool },
+    Sixel,
+    Kitty,
+}
+
+// Cover art of the current song drawn next to the now playing bar
+pub struct AlbumArt {
+    protocol: Option<Protocol>,
+    path: Option<PathBuf>,
+    image: Option<DynamicImage>,
+    // area the art was last rendered to and the area the graphics protocol output was written
+    // to, the image is only written again when these differ
+    area: Rect,
+    written: Option<Rect>,
+    // resizing the terminal clears it, so the image has to be written again
+    terminal_size: Rect,
+}
+
+impl AlbumArt {
+    pub fn new(mode: ArtMode) -> Self {
+        let protocol = match mode {
+            ArtMode::Auto => detect_protocol(),
+            ArtMode::HalfBlocks => Some(Protocol::HalfBlocks {
+                truecolor: supports_truecolor(),
+            }),
+            ArtMode::Sixel => Some(Protocol::Sixel),
+            ArtMode::Kitty => Some(Protocol::Kitty),
+            ArtMode::Off => None,
+        };
+        AlbumArt {
+            protocol,
+            path: None,
+            image: None,
+            area: Rect::default(),
+            written: None,
+            terminal_size: Rect::default(),
+        }
+    }
+
+    // Load the artwork at `path` if it isn't the one already shown. Returns true if the image
+    // changed while using a graphics protocol, in which case the terminal has to be cleared to
+    // get rid of the old image.
+    pub fn set_artwork(&mut self, path: Option<PathBuf>) -> bool {
+        if self.protocol.is_none() || path == self.path {
+            return false;
+        }
+        self.image = match &path {
+            Some(path) => match image::open(path) {
+                Ok(image) => Some(image),
+                Err(err) => {
+                    warn!("Could not load artwork {}: {}", path.display(), err);
+                    None
+                }
+            },
+            None => None,
+        };
+        self.path = path;
+        self.written.take().is_some()
+    }
+
+    // Width of the area the art needs for a given height, 0 when there is nothing to show
+    pub fn width(&self, height: u16) -> u16 {
+        match self.image {
+            // cells are about twice as tall as they are wide
+            Some(_) => height.saturating_mul(2),
+            None => 0,
+        }
+    }
+
+    pub fn render(&mut self, frame: &mut Frame<impl tui::backend::Backend>, area: Rect) {
+        let image = match &self.image {
+            Some(image) if area.width > 0 && area.height > 0 => image,
+            _ => return,
+        };
+        if area != self.area || frame.size() != self.terminal_size {
+            self.area = area;
+            self.terminal_size = frame.size();
+            self.written = None;
+        }
+        match self.protocol {
+            Some(Protocol::HalfBlocks { truecolor }) => {
+                let pixels = image
+                    .resize(
+                        area.width as u32,
+                        area.height as u32 * 2,
+                        FilterType::Triangle,
+                    )
+                    .to_rgb8();
+                frame.render_widget(HalfBlocks { pixels, truecolor }, area);
+            }
+            // the image is written over these cells by `flush` after the frame is drawn
+            Some(_) => frame.render_widget(Clear, area),
+            None => (),
+        }
+    }
+
+    // Write the image using the graphics protocol, must be called after the frame is drawn
+    pub fn flush(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let image = match &self.image {
+            Some(image) if self.written != Some(self.area) && self.area.area() > 0 => image,
+            _ => return Ok(()),
+        };
+        let area = self.area;
+        let data = match self.protocol {
+            Some(Protocol::Sixel) => sixel(&image.resize(
+                area.width as u32 * SIXEL_CELL_SIZE.0,
+                area.height as u32 * SIXEL_CELL_SIZE.1,
+                FilterType::Triangle,
+            )),
+            Some(Protocol::Kitty) => match &self.path {
+                // the cached thumbnails are already PNGs which kitty can decode itself
+                Some(path) => kitty(&fs::read(path)?, area, image),
+                None => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        queue!(out, cursor::SavePosition, cursor::MoveTo(area.x, area.y))?;
+        out.write_all(&data)?;
+        queue!(out, cursor::RestorePosition)?;
+        out.flush()?;
+        self.written = Some(area);
+        Ok(())
+    }
+}
+
+struct HalfBlocks {
+    pixels: RgbImage,
+    truecolor: bool,
+}
+
+impl Widget for HalfBlocks {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (width, height) = self.pixels.dimensions();
+        // center the image in the area
+        let x_offset = (area.width as u32).saturating_sub(width) / 2;
+        let y_offset = ((area.height as u32 * 2).saturating_sub(height) / 2) / 2;
+        let color = |x: u32, y: u32| -> Color {
+            let [r, g, b] = self.pixels.get_pixel(x, y).0;
+            match self.truecolor {
+                true => Color::Rgb(r, g, b),
+                false => Color::Indexed(ansi_256(r, g, b)),
+            }
+        };
+        for y in (0..height).step_by(2) {
+            for x in 0..width {
+                let cell = buf.get_mut(
+                    area.x + (x_offset + x) as u16,
+                    area.y + (y_offset + y / 2) as u16,
+                );
+                cell.set_symbol("▀").set_fg(color(x, y));
+                if y + 1 < height {
+                    cell.set_bg(color(x, y + 1));
+                }
+            }
+        }
+    }
+}
+
+fn detect_protocol() -> Option<Protocol> {
+    let term = env::var("TERM").unwrap_or_default();
+    let term_program = env::var("TERM_PROGRAM").unwrap_or_default();
+    // the linux console and dumb terminals can't show anything useful
+    if term.is_empty() || term == "dumb" || term == "linux" {
+        return None;
+    }
+    if env::var("KITTY_WINDOW_ID").is_ok() || term.contains("kitty") || term == "xterm-ghostty" {
+        return Some(Protocol::Kitty);
+    }
+    if term.starts_with("foot") || term.starts_with("mlterm") || term.contains("sixel") {
+        return Some(Protocol::Sixel);
+    }
+    if term_program == "WezTerm" {
+        return Some(Protocol::Sixel);
+    }
+    Some(Protocol::HalfBlocks {
+        truecolor: supports_truecolor(),
+    })
+}
+
+fn supports_truecolor() -> bool {
+    matches!(
+        env::var("COLORTERM").as_deref(),
+        Ok("truecolor") | Ok("24bit")
+    )
+}
+
+// closest colour in the 6x6x6 cube of the 256 colour palette
+fn ansi_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| (c as u16 * 5 / 255) as u8;
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+// Encode an image as sixels using the same 6x6x6 colour cube as `ansi_256`
+fn sixel(image: &DynamicImage) -> Vec<u8> {
+    let pixels = image.to_rgb8();
+    let (width, height) = pixels.dimensions();
+    let index = |x: u32, y: u32| {
+        let [r, g, b] = pixels.get_pixel(x, y).0;
+        ansi_256(r, g, b) - 16
+    };
+
+    let mut out = format!("\x1bPq\"1;1;{};{}", width, height).into_bytes();
+    for color in 0..216u16 {
+        let percent = |level: u16| level * 100 / 5;
+        out.extend(
+            format!(
+                "#{};2;{};{};{}",
+                color,
+                percent(color / 36),
+                percent(color / 6 % 6),
+                percent(color % 6)
+            )
+            .bytes(),
+        );
+    }
+    for band in (0..height).step_by(6) {
+        // sixel rows of each colour used in this band of 6 pixel rows
+        let mut rows: BTreeMap<u8, Vec<u8>> = BTreeMap::new();
+        for y in band..(band + 6).min(height) {
+            for x in 0..width {
+                let row = rows
+                    .entry(index(x, y))
+                    .or_insert_with(|| vec![0; width as usize]);
+                row[x as usize] |= 1 << (y - band);
+            }
+        }
+        for (color, row) in rows {
+            out.extend(format!("#{}", color).bytes());
+            // run length encode repeated sixels
+            let mut x = 0;
+            while x < row.len() {
+                let run = row[x..].iter().take_while(|s| **s == row[x]).count();
+                let sixel = (row[x] + 63) as char;
+                match run {
+                    1..=3 => out.extend(std::iter::repeat_n(sixel as u8, run)),
+                    _ => out.extend(format!("!{}{}", run, sixel).bytes()),
+                }
+                x += run;
+            }
+            out.push(b'$');
+        }
+        out.push(b'-');
+    }
+    out.extend(b"\x1b\\");
+    out
+}
+
+// Transmit and display a PNG with the kitty graphics protocol, scaled to fit in `area`
+fn kitty(png: &[u8], area: Rect, image: &DynamicImage) -> Vec<u8> {
+    // keep the aspect ratio, cells are about twice as tall as they are wide
+    let (width, height) = (image.width().max(1), image.height().max(1));
+    let columns = (area.width as u32).min(area.height as u32 * 2 * width / height);
+    let rows = (area.height as u32).min(columns * height / (width * 2));
+
+    let payload = STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+    // delete previously shown images first
+    let mut out = b"\x1b_Ga=d,q=2\x1b\\".to_vec();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = (i + 1 < chunks.len()) as u8;
+        let control = match i {
+            0 => format!(
+                "f=100,a=T,q=2,C=1,z=-1,c={},r={},m={}",
+                columns.max(1),
+                rows.max(1),
+                more
+            ),
+            _ => format!("m={}", more),
+        };
+        out.extend(format!("\x1b_G{};", control).bytes());
+        out.extend_from_slice(chunk);
+        out.extend(b"\x1b\\");
+    }
+    out
+}
@@ -0,0 +1,251 @@
+use tui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::Spans,
+    widgets::{Block, Borders, List, ListItem},
+    Frame,
+};
+
+use crate::library::{query::Query, song::Song, Library};
+use crate::ui::widgets::stateful_list::StatefulList;
+
+const CATEGORIES: [&str; 4] = ["Albums", "Artists", "Genres", "Years"];
+
+#[derive(Clone)]
+enum Node {
+    Root,
+    AlbumArtists,
+    Albums(String),
+    AlbumTracks(String, String),
+    Artists,
+    ArtistTracks(String),
+    Genres,
+    GenreTracks(String),
+    Years,
+    YearTracks(String),
+    Searches,
+    SearchTracks(String),
+}
+
+// Drill down view over the library indexes: category -> (album artist ->) group -> tracks
+pub struct Browser {
+    node: Node,
+    // nodes above the current one along with what was selected in them
+    parents: Vec<(Node, Option<usize>)>,
+    pub list: StatefulList<String>,
+    songs: Vec<Song>,
+    // saved searches from the config, listed under their own category when there are any
+    searches: Vec<(String, Query)>,
+}
+
+impl Browser {
+    pub fn new() -> Self {
+        let mut browser = Browser {
+            node: Node::Root,
+            parents: Vec::new(),
+            list: StatefulList::with_items(Vec::new()),
+            songs: Vec::new(),
+            searches: Vec::new(),
+        };
+        browser.list.items = browser.categories();
+        browser.list.state.select(Some(0));
+        browser
+    }
+
+    pub fn set_searches(&mut self, searches: Vec<(String, Query)>) {
+        self.searches = searches;
+        if let Node::Root = self.node {
+            self.list.items = self.categories();
+        }
+    }
+
+    fn categories(&self) -> Vec<String> {
+        let mut categories: Vec<String> = CATEGORIES.iter().map(|c| c.to_string()).collect();
+        if !self.searches.is_empty() {
+            categories.push("Saved searches".to_string());
+        }
+        categories
+    }
+
+    // reload the items shown for the current node, e.g. after the library changed
+    pub fn refresh(&mut self, library: &Library) {
+        let to_strings = |keys: Vec<&str>| keys.iter().map(|k| k.to_string()).collect();
+        let songs: Vec<&Song> = match &self.node {
+            Node::Root => {
+                self.list.items = self.categories();
+                Vec::new()
+            }
+            Node::AlbumArtists => {
+                self.list.items = to_strings(library.album_artists());
+                Vec::new()
+            }
+            Node::Albums(album_artist) => {
+                self.list.items = to_strings(library.albums(album_artist));
+                Vec::new()
+            }
+            Node::Artists => {
+                self.list.items = to_strings(library.artists());
+                Vec::new()
+            }
+            Node::Genres => {
+                self.list.items = to_strings(library.genres());
+                Vec::new()
+            }
+            Node::Years => {
+                self.list.items = to_strings(library.years());
+                Vec::new()
+            }
+            Node::AlbumTracks(album_artist, album) => library.album_tracks(album_artist, album),
+            Node::ArtistTracks(artist) => library.artist_tracks(artist),
+            Node::GenreTracks(genre) => library.genre_tracks(genre),
+            Node::YearTracks(year) => library.year_tracks(year),
+            Node::Searches => {
+                self.list.items = self.searches.iter().map(|(name, _)| name.clone()).collect();
+                Vec::new()
+            }
+            Node::SearchTracks(name) => match self.searches.iter().find(|(n, _)| n == name) {
+                Some((_, query)) => library
+                    .query(query)
+                    .into_iter()
+                    .map(|hit| hit.song)
+                    .collect(),
+                None => Vec::new(),
+            },
+        };
+        if self.is_track_list() {
+            self.songs = songs.into_iter().cloned().collect();
+            self.list.items = self.songs.iter().map(track_label).collect();
+        } else {
+            self.songs.clear();
+        }
+
+        match self.list.state.selected() {
+            _ if self.list.items.is_empty() => self.list.unselect(),
+            Some(idx) if idx >= self.list.items.len() => {
+                self.list.state.select(Some(self.list.items.len() - 1))
+            }
+            None => self.list.state.select(Some(0)),
+            _ => (),
+        }
+    }
+
+    pub fn selected_song(&self) -> Option<&Song> {
+        match self.list.state.selected() {
+            Some(idx) => self.songs.get(idx),
+            None => None,
+        }
+    }
+
+    // tracks of the current view, empty when it lists categories
+    pub fn songs(&self) -> &[Song] {
+        &self.songs
+    }
+
+    // Open the selected entry, returning the selected song if the current view lists tracks
+    pub fn enter(&mut self, library: &Library) -> Option<Song> {
+        let idx = self.list.state.selected()?;
+        if self.is_track_list() {
+            return self.songs.get(idx).cloned();
+        }
+        let label = self.list.items.get(idx)?.to_owned();
+        let child = match &self.node {
+            Node::Root => match idx {
+                0 => Node::AlbumArtists,
+                1 => Node::Artists,
+                2 => Node::Genres,
+                3 => Node::Years,
+                _ => Node::Searches,
+            },
+            Node::AlbumArtists => Node::Albums(label),
+            Node::Albums(album_artist) => Node::AlbumTracks(album_artist.to_owned(), label),
+            Node::Artists => Node::ArtistTracks(label),
+            Node::Genres => Node::GenreTracks(label),
+            Node::Years => Node::YearTracks(label),
+            Node::Searches => Node::SearchTracks(label),
+            _ => return None,
+        };
+        self.parents.push((self.node.clone(), Some(idx)));
+        self.node = child;
+        self.list.state.select(Some(0));
+        self.refresh(library);
+        None
+    }
+
+    // Go up one level, returns false if already at the top
+    pub fn back(&mut self, library: &Library) -> bool {
+        match self.parents.pop() {
+            Some((node, selected)) => {
+                self.node = node;
+                self.list.state.select(selected);
+                self.refresh(library);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn is_track_list(&self) -> bool {
+        matches!(
+            self.node,
+            Node::AlbumTracks(..)
+                | Node::ArtistTracks(_)
+                | Node::GenreTracks(_)
+                | Node::YearTracks(_)
+                | Node::SearchTracks(_)
+        )
+    }
+
+    fn title(&self) -> String {
+        match &self.node {
+            Node::Root => "Browse".to_string(),
+            Node::AlbumArtists => "Browse / Albums".to_string(),
+            Node::Albums(album_artist) => format!("Browse / Albums / {}", album_artist),
+            Node::AlbumTracks(album_artist, album) => {
+                format!("Browse / Albums / {} / {}", album_artist, album)
+            }
+            Node::Artists => "Browse / Artists".to_string(),
+            Node::ArtistTracks(artist) => format!("Browse / Artists / {}", artist),
+            Node::Genres => "Browse / Genres".to_string(),
+            Node::GenreTracks(genre) => format!("Browse / Genres / {}", genre),
+            Node::Years => "Browse / Years".to_string(),
+            Node::YearTracks(year) => format!("Browse / Years / {}", year),
+            Node::Searches => "Browse / Saved searches".to_string(),
+            Node::SearchTracks(name) => format!("Browse / Saved searches / {}", name),
+        }
+    }
+}
+
+fn track_label(song: &Song) -> String {
+    let album: String = song.album_title.chars().take(16).collect();
+    format!(
+        "{: <16} {:>3} {} - {}",
+        album,
+        song.track_number.clone().unwrap_or_default(),
+        song.title,
+        song.track_artist
+    )
+}
+
+pub fn render(frame: &mut Frame<impl tui::backend::Backend>, area: Rect, browser: &mut Browser) {
+    let items: Vec<ListItem> = browser
+        .list
+        .items
+        .iter()
+        .map(|label| ListItem::new(vec![Spans::from(label.to_owned())]))
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(browser.title()),
+        )
+        .highlight_style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">> ");
+
+    frame.render_stateful_widget(list, area, &mut browser.list.state);
+}
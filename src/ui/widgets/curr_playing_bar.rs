@@ -11,7 +11,12 @@ use tui::{
     Frame,
 };
 
-use crate::{queue::SongQueue, state::AppState, utils::constants::PlayerStates};
+use crate::{
+    library::song::Song,
+    queue::{RepeatMode, ShuffleMode},
+    state::AppState,
+    utils::constants::PlayerStates,
+};
 
 pub fn render(frame: &mut Frame<impl tui::backend::Backend>, area: Rect, state: &AppState) {
     let song_title = match &state.player.curr_song {
@@ -19,25 +24,39 @@ pub fn render(frame: &mut Frame<impl tui::backend::Backend>, area: Rect, state:
         Some(song) => Span::raw(song.title.to_owned()),
     };
 
+    // the playing song is a copy, the library has its current rating
+    let rating = match &state.player.curr_song {
+        None => Span::raw(""),
+        Some(song) => match state.library.song(&song.path) {
+            Some(song) => Span::styled(rating(song), Style::default().fg(Color::Yellow)),
+            None => Span::raw(""),
+        },
+    };
+
     let song_artist = match &state.player.curr_song {
         None => Span::raw(""),
         Some(song) => Span::raw(song.track_artist.to_owned()),
     };
 
-    let curr_time_secs = state.player.progress.as_secs();
+    let curr_time_ms = state.player.progress.as_millis() as u64;
 
-    let total_time_secs = match &state.player.curr_song {
+    let total_time_ms = match &state.player.curr_song {
         None => 0,
-        Some(song) => song.duration_secs,
+        Some(song) => song.duration_ms,
     };
 
-    let curr_time_span = Span::raw(readable_time(curr_time_secs));
+    let curr_time_span = Span::raw(readable_time(curr_time_ms / 1000));
 
-    let total_time_span = Span::raw(readable_time(total_time_secs));
+    let total_time_span = Span::raw(readable_time(total_time_ms / 1000));
 
-    let percentage_played: f64 = match total_time_secs {
+    let percentage_played: f64 = match total_time_ms {
         0 => 0.,
-        _ => (curr_time_secs as f64) / (total_time_secs as f64),
+        _ => ((curr_time_ms as f64) / (total_time_ms as f64)).min(1.),
+    };
+
+    let audio_info = match &state.player.curr_song {
+        None => Span::raw(""),
+        Some(song) => Span::raw(audio_info(song)),
     };
 
     let time_gauge = Gauge::default()
@@ -55,6 +74,21 @@ pub fn render(frame: &mut Frame<impl tui::backend::Backend>, area: Rect, state:
         PlayerStates::PAUSED => Span::raw("Paused"),
     };
 
+    let shuffle = match state.queue.shuffle() {
+        ShuffleMode::Off => Span::raw(""),
+        mode => Span::raw(format!("  shuffle: {}", mode.label())),
+    };
+
+    let repeat = match state.queue.repeat() {
+        RepeatMode::Off => Span::raw(""),
+        mode => Span::raw(format!("  repeat: {}", mode.label())),
+    };
+
+    let radio = match state.queue.radio() {
+        true => Span::raw("  radio"),
+        false => Span::raw(""),
+    };
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
@@ -74,9 +108,13 @@ pub fn render(frame: &mut Frame<impl tui::backend::Backend>, area: Rect, state:
 
     let player_status_text = vec![
         Spans::from(vec![curr_time_span, Span::raw("/"), total_time_span]),
-        Spans::from(play_status),
+        Spans::from(vec![play_status, shuffle, repeat, radio]),
     ];
-    let song_status_text = vec![Spans::from(song_title), Spans::from(song_artist)];
+    let song_status_text = vec![
+        Spans::from(vec![song_title, rating]),
+        Spans::from(song_artist),
+    ];
+    let audio_info_text = vec![Spans::from(audio_info)];
 
     frame.render_widget(Clear, area);
     frame.render_widget(
@@ -87,6 +125,10 @@ pub fn render(frame: &mut Frame<impl tui::backend::Backend>, area: Rect, state:
         Paragraph::new(song_status_text).alignment(Left),
         player_info_chunks[1],
     );
+    frame.render_widget(
+        Paragraph::new(audio_info_text).alignment(Left),
+        player_info_chunks[2],
+    );
     frame.render_widget(time_gauge, chunks[1]);
 }
 
@@ -96,3 +138,35 @@ fn readable_time(secs: u64) -> String {
 
     return format!("{:02}:{:02}", mins, secs);
 }
+
+// e.g. "FLAC 44.1 kHz 2ch 912 kbps"
+fn audio_info(song: &Song) -> String {
+    let mut info = Vec::new();
+    if let Some(codec) = &song.codec {
+        info.push(codec.to_owned());
+    }
+    if let Some(sample_rate) = song.sample_rate {
+        info.push(format!("{} kHz", sample_rate as f64 / 1000.));
+    }
+    if let Some(channels) = song.channels {
+        info.push(format!("{}ch", channels));
+    }
+    if let Some(bitrate) = song.bitrate {
+        info.push(format!("{} kbps", bitrate));
+    }
+    info.join(" ")
+}
+
+// stars and a heart for loved songs, empty for unrated songs that aren't loved
+fn rating(song: &Song) -> String {
+    let mut rating = String::new();
+    if song.rating > 0 {
+        rating.push_str("  ");
+        rating.push_str(&"★".repeat(song.rating as usize));
+        rating.push_str(&"☆".repeat(5usize.saturating_sub(song.rating as usize)));
+    }
+    if song.loved {
+        rating.push_str("  ♥");
+    }
+    rating
+}
@@ -0,0 +1,42 @@
+use tui::{
+    layout::Alignment,
+    style::{Color, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::library::report::ImportReport;
+use crate::ui::helper;
+
+// popup listing every file that failed to import along with the reason
+pub fn render(frame: &mut Frame<impl tui::backend::Backend>, report: &ImportReport, scroll: u16) {
+    let area = helper::centered_rect(80, 80, frame.size());
+    let block = Block::default()
+        .title(format!("Import report: {}", report))
+        .borders(Borders::ALL);
+
+    let lines: Vec<Spans> = if report.failed.is_empty() {
+        vec![Spans::from("No files failed to import")]
+    } else {
+        report
+            .failed
+            .iter()
+            .map(|err| {
+                Spans::from(vec![
+                    Span::styled(err.reason(), Style::default().fg(Color::Red)),
+                    Span::raw("  "),
+                    Span::raw(err.path().to_owned()),
+                ])
+            })
+            .collect()
+    };
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .alignment(Alignment::Left)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use tui::{
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use crate::library::lyrics::Lyrics;
+
+// Synced lyrics highlight the current line and keep it in the middle of the pane, unsynced
+// lyrics are scrolled with `scroll`
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    lyrics: Option<&Lyrics>,
+    progress: Duration,
+    scroll: u16,
+) {
+    let block = Block::default().borders(Borders::ALL).title("Lyrics");
+    let lyrics = match lyrics {
+        Some(lyrics) => lyrics,
+        None => {
+            let paragraph = Paragraph::new("No lyrics found")
+                .block(block)
+                .alignment(Alignment::Center);
+            frame.render_widget(paragraph, area);
+            return;
+        }
+    };
+
+    let current = lyrics.current_line(progress);
+    let text: Vec<Spans> = lyrics
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let style = match current {
+                Some(current) if current == idx => Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+                Some(_) => Style::default().fg(Color::Gray),
+                None => Style::default(),
+            };
+            Spans::from(Span::styled(line.text.to_owned(), style))
+        })
+        .collect();
+
+    let scroll = match current {
+        Some(current) => {
+            let half_height = area.height.saturating_sub(2) / 2;
+            (current as u16).saturating_sub(half_height)
+        }
+        None => scroll,
+    };
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(Alignment::Center)
+        .scroll((scroll, 0));
+    frame.render_widget(paragraph, area);
+}
@@ -1,3 +1,11 @@
+pub mod album_art;
+pub mod browser;
 pub mod curr_playing_bar;
+pub mod import_report;
+pub mod lyrics;
+pub mod playlists;
+pub mod queue;
 pub mod search_popup;
 pub mod stateful_list;
+pub mod stats;
+pub mod tag_editor;
@@ -0,0 +1,288 @@
+use std::path::Path;
+
+use tui::{
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    Frame,
+};
+
+use crate::library::{playlist::Playlists, song::Song, Library};
+use crate::ui::helper;
+use crate::ui::widgets::stateful_list::StatefulList;
+
+// Rename, Export and Delete apply to the selected playlist, the selection can't change while
+// typing
+#[derive(Clone)]
+pub enum Prompt {
+    Create,
+    // name then rules of a new smart playlist
+    SmartName,
+    // rules for the smart playlist with this name, or the selected one
+    SmartRules(Option<String>),
+    Rename,
+    // path of the file to import or export to
+    Import,
+    Export,
+    // confirm deleting the selected playlist, shows its name
+    Delete,
+}
+
+// List of playlists, or the tracks of the open one
+pub struct PlaylistView {
+    pub list: StatefulList<String>,
+    // index of the open playlist and the selection to restore when going back
+    open: Option<(usize, Option<usize>)>,
+    // playlist name or file path being typed
+    pub prompt: Option<(Prompt, String)>,
+    pub error: Option<String>,
+}
+
+impl PlaylistView {
+    pub fn new() -> Self {
+        PlaylistView {
+            list: StatefulList::with_items(Vec::new()),
+            open: None,
+            prompt: None,
+            error: None,
+        }
+    }
+
+    // reload the shown items after the playlists or the library changed
+    pub fn refresh(&mut self, playlists: &Playlists, library: &Library) {
+        self.list.items = match self.open.and_then(|(idx, _)| playlists.get(idx)) {
+            Some(playlist) => playlist
+                .paths
+                .iter()
+                .map(|path| match library.song(path) {
+                    Some(song) => track_label(song),
+                    None => format!("{} (missing)", file_name(path)),
+                })
+                .collect(),
+            None => {
+                self.open = None;
+                playlists
+                    .list()
+                    .iter()
+                    .map(|p| match p.is_smart() {
+                        true => format!("{} (smart, {} tracks)", p.name, p.paths.len()),
+                        false => format!("{} ({} tracks)", p.name, p.paths.len()),
+                    })
+                    .collect()
+            }
+        };
+        match self.list.state.selected() {
+            _ if self.list.items.is_empty() => self.list.unselect(),
+            Some(idx) if idx >= self.list.items.len() => {
+                self.list.state.select(Some(self.list.items.len() - 1))
+            }
+            None => self.list.state.select(Some(0)),
+            _ => (),
+        }
+    }
+
+    // open the selected playlist, returns false if one is already open
+    pub fn open(&mut self, playlists: &Playlists, library: &Library) -> bool {
+        if self.open.is_some() {
+            return false;
+        }
+        if let Some(idx) = self.list.state.selected() {
+            self.open = Some((idx, Some(idx)));
+            self.list.state.select(Some(0));
+            self.refresh(playlists, library);
+        }
+        true
+    }
+
+    // back to the list of playlists, returns false if already there
+    pub fn back(&mut self, playlists: &Playlists, library: &Library) -> bool {
+        match self.open.take() {
+            Some((_, selected)) => {
+                self.list.state.select(selected);
+                self.refresh(playlists, library);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // the open playlist, or the selected one in the list of playlists
+    pub fn playlist(&self) -> Option<usize> {
+        match self.open {
+            Some((idx, _)) => Some(idx),
+            None => self.list.state.selected(),
+        }
+    }
+
+    // position of the selected track in the open playlist
+    pub fn track(&self) -> Option<usize> {
+        self.open?;
+        self.list.state.selected()
+    }
+
+    pub fn selected_song<'a>(
+        &self,
+        playlists: &Playlists,
+        library: &'a Library,
+    ) -> Option<&'a Song> {
+        let path = playlists.get(self.playlist()?)?.paths.get(self.track()?)?;
+        library.song(path)
+    }
+
+    fn title(&self, playlists: &Playlists) -> String {
+        match self.open.and_then(|(idx, _)| playlists.get(idx)) {
+            Some(playlist) => format!("Playlists / {}", playlist.name),
+            None => "Playlists".to_string(),
+        }
+    }
+}
+
+// Popup for picking the playlist to add `song` to, smart playlists aren't listed
+pub struct PlaylistPicker {
+    pub song: Song,
+    pub list: StatefulList<String>,
+    // index in `Playlists` of each listed playlist
+    indices: Vec<usize>,
+}
+
+impl PlaylistPicker {
+    pub fn new(song: Song, playlists: &Playlists) -> Self {
+        let (indices, names) = playlists
+            .list()
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| !p.is_smart())
+            .map(|(idx, p)| (idx, p.name.clone()))
+            .unzip();
+        let mut list = StatefulList::with_items(names);
+        if !list.items.is_empty() {
+            list.state.select(Some(0));
+        }
+        PlaylistPicker {
+            song,
+            list,
+            indices,
+        }
+    }
+
+    // index of the selected playlist
+    pub fn playlist(&self) -> Option<usize> {
+        self.indices.get(self.list.state.selected()?).copied()
+    }
+}
+
+fn track_label(song: &Song) -> String {
+    format!("{} - {}", song.title, song.track_artist)
+}
+
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+}
+
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    view: &mut PlaylistView,
+    playlists: &Playlists,
+) {
+    let items: Vec<ListItem> = view
+        .list
+        .items
+        .iter()
+        .map(|label| ListItem::new(vec![Spans::from(label.to_owned())]))
+        .collect();
+    let title = match &view.error {
+        Some(err) => Spans::from(vec![
+            Span::raw(view.title(playlists)),
+            Span::styled(format!("  {}", err), Style::default().fg(Color::Red)),
+        ]),
+        None => Spans::from(view.title(playlists)),
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">> ");
+    frame.render_stateful_widget(list, area, &mut view.list.state);
+
+    if let Some((Prompt::Delete, name)) = &view.prompt {
+        let area = helper::centered_rect(60, 20, frame.size());
+        let paragraph = Paragraph::new(vec![
+            Spans::from(format!("Delete \"{}\" and its file?", name)),
+            Spans::from(""),
+            Spans::from(Span::styled(
+                "Enter: delete  Esc: cancel",
+                Style::default().fg(Color::Gray),
+            )),
+        ])
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Delete playlist"),
+        )
+        .alignment(Alignment::Left);
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    } else if let Some((prompt, name)) = &view.prompt {
+        let title = match prompt {
+            Prompt::Create => "New playlist",
+            Prompt::SmartName => "New smart playlist",
+            Prompt::SmartRules(_) => "Rules, e.g. genre:jazz lastplayed:>1y sort:-plays limit:50",
+            Prompt::Rename => "Rename playlist",
+            Prompt::Import => "Import playlist (.m3u, .m3u8, .pls or .xspf)",
+            Prompt::Export => "Export playlist to (.m3u, .m3u8, .pls or .xspf)",
+            Prompt::Delete => return,
+        };
+        let area = helper::centered_rect(60, 20, frame.size());
+        let paragraph = Paragraph::new(vec![
+            Spans::from(Span::styled(
+                format!("{}_", name),
+                Style::default().fg(Color::Cyan),
+            )),
+            Spans::from(""),
+            Spans::from(Span::styled(
+                "Enter: save  Esc: cancel",
+                Style::default().fg(Color::Gray),
+            )),
+        ])
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .alignment(Alignment::Left);
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+}
+
+pub fn render_picker(frame: &mut Frame<impl tui::backend::Backend>, picker: &mut PlaylistPicker) {
+    let area = helper::centered_rect(40, 50, frame.size());
+    let title = format!("Add \"{}\" to", picker.song.title);
+    frame.render_widget(Clear, area);
+    if picker.list.items.is_empty() {
+        let paragraph = Paragraph::new("No playlists yet, create one in the Playlists tab")
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .alignment(Alignment::Center);
+        frame.render_widget(paragraph, area);
+        return;
+    }
+    let items: Vec<ListItem> = picker
+        .list
+        .items
+        .iter()
+        .map(|name| ListItem::new(vec![Spans::from(name.to_owned())]))
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">> ");
+    frame.render_stateful_widget(list, area, &mut picker.list.state);
+}
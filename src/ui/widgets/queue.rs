@@ -0,0 +1,96 @@
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Spans,
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::library::song::Song;
+use crate::queue::SongQueue;
+
+// Selected position in the queue, counting the songs to play next first like `SongQueue`
+pub struct QueueView {
+    pub selected: Option<usize>,
+}
+
+impl QueueView {
+    pub fn new() -> Self {
+        QueueView { selected: None }
+    }
+
+    pub fn up(&mut self, queue: &SongQueue) {
+        self.selected = match self.selected {
+            _ if queue.is_empty() => None,
+            Some(0) | None => Some(queue.len() - 1),
+            Some(pos) => Some(pos.min(queue.len()) - 1),
+        };
+    }
+
+    pub fn down(&mut self, queue: &SongQueue) {
+        self.selected = match self.selected {
+            _ if queue.is_empty() => None,
+            Some(pos) if pos + 1 < queue.len() => Some(pos + 1),
+            _ => Some(0),
+        };
+    }
+
+    pub fn selected_song<'a>(&self, queue: &'a SongQueue) -> Option<&'a Song> {
+        queue.get(self.selected?)
+    }
+
+    // keep the selection in the queue as songs are played or removed
+    pub fn clamp(&mut self, queue: &SongQueue) {
+        self.selected = match self.selected {
+            _ if queue.is_empty() => None,
+            Some(pos) => Some(pos.min(queue.len() - 1)),
+            None => Some(0),
+        };
+    }
+}
+
+fn song_item(song: &Song) -> ListItem<'static> {
+    ListItem::new(Spans::from(format!(
+        "{} - {}  ({})",
+        song.title, song.track_artist, song.album_title
+    )))
+}
+
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    view: &mut QueueView,
+    queue: &SongQueue,
+) {
+    view.clamp(queue);
+    let immediate_len = queue.immediate().len();
+    // the songs to play next get as much room as they need, up to half of the pane
+    let immediate_height = (immediate_len as u16 + 2).clamp(3, (area.height / 2).max(3));
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(immediate_height), Constraint::Min(0)].as_ref())
+        .split(area);
+    let highlight_style = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+
+    let mut immediate_state = ListState::default();
+    immediate_state.select(view.selected.filter(|pos| *pos < immediate_len));
+    let items: Vec<ListItem> = queue.immediate().iter().map(song_item).collect();
+    let title = format!("Playing next ({})", immediate_len);
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(highlight_style)
+        .highlight_symbol(">> ");
+    frame.render_stateful_widget(list, chunks[0], &mut immediate_state);
+
+    let mut upcoming_state = ListState::default();
+    upcoming_state.select(view.selected.and_then(|pos| pos.checked_sub(immediate_len)));
+    let items: Vec<ListItem> = queue.upcoming().iter().map(song_item).collect();
+    let title = format!("Up next ({})", queue.upcoming().len());
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(highlight_style)
+        .highlight_symbol(">> ");
+    frame.render_stateful_widget(list, chunks[1], &mut upcoming_state);
+}
@@ -0,0 +1,63 @@
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    text::Spans,
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame,
+};
+
+use crate::library::history::{History, Period};
+
+// entries in each of the top lists
+const TOP: usize = 50;
+
+// The most played artists, albums and tracks since the start of `period`
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    history: &History,
+    period: Period,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .split(area);
+    let (plays, listened) = history.total(period);
+    let minutes = listened.as_secs() / 60;
+    let summary = Paragraph::new(format!(
+        "{} plays, {}h {:02}m listened",
+        plays,
+        minutes / 60,
+        minutes % 60
+    ))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Stats ({})", period.label())),
+    );
+    frame.render_widget(summary, chunks[0]);
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Ratio(1, 3),
+                Constraint::Ratio(1, 3),
+                Constraint::Ratio(1, 3),
+            ]
+            .as_ref(),
+        )
+        .split(chunks[1]);
+    let tops = [
+        ("Top artists", history.top_artists(period, TOP)),
+        ("Top albums", history.top_albums(period, TOP)),
+        ("Top tracks", history.top_tracks(period, TOP)),
+    ];
+    for ((title, top), area) in tops.into_iter().zip(columns) {
+        let items: Vec<ListItem> = top
+            .into_iter()
+            .map(|(name, plays)| ListItem::new(Spans::from(format!("{:>4}  {}", plays, name))))
+            .collect();
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(list, area);
+    }
+}
@@ -0,0 +1,137 @@
+use tui::{
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::library::song::Song;
+use crate::library::tag_edit::TagField;
+use crate::ui::helper;
+
+// Form for editing the tags of one song, or the album wide tags of every track of an album
+pub struct TagEditor {
+    pub paths: Vec<String>,
+    title: String,
+    fields: Vec<TagField>,
+    values: Vec<String>,
+    original: Vec<String>,
+    // fields that differ between the tracks being edited, left unchanged unless typed in
+    mixed: Vec<bool>,
+    selected: usize,
+    pub error: Option<String>,
+}
+
+impl TagEditor {
+    pub fn for_song(song: &Song) -> Self {
+        TagEditor::new(format!("Edit: {}", song.title), &TagField::ALL, &[song])
+    }
+
+    pub fn for_album(songs: &[&Song]) -> Self {
+        let title = match songs.first() {
+            Some(song) => format!("Edit album: {} ({} tracks)", song.album_title, songs.len()),
+            None => "Edit album".to_string(),
+        };
+        TagEditor::new(title, &TagField::ALBUM, songs)
+    }
+
+    fn new(title: String, fields: &[TagField], songs: &[&Song]) -> Self {
+        let mut values = Vec::new();
+        let mut mixed = Vec::new();
+        for field in fields {
+            let mut song_values = songs.iter().map(|song| field.value(song));
+            let first = song_values.next().unwrap_or_default();
+            let is_mixed = song_values.any(|value| value != first);
+            values.push(if is_mixed { String::new() } else { first });
+            mixed.push(is_mixed);
+        }
+        TagEditor {
+            paths: songs.iter().map(|song| song.path.to_owned()).collect(),
+            title,
+            fields: fields.to_vec(),
+            original: values.clone(),
+            values,
+            mixed,
+            selected: 0,
+            error: None,
+        }
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        if self.selected + 1 < self.fields.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn push(&mut self, ch: char) {
+        self.values[self.selected].push(ch);
+        self.mixed[self.selected] = false;
+    }
+
+    pub fn backspace(&mut self) {
+        self.values[self.selected].pop();
+    }
+
+    // fields whose value was changed in the form
+    pub fn changes(&self) -> Vec<(TagField, String)> {
+        self.fields
+            .iter()
+            .zip(self.values.iter().zip(self.original.iter()))
+            .zip(self.mixed.iter())
+            .filter(|((_, (value, original)), mixed)| !**mixed && value != original)
+            .map(|((field, (value, _)), _)| (*field, value.to_owned()))
+            .collect()
+    }
+}
+
+pub fn render(frame: &mut Frame<impl tui::backend::Backend>, editor: &TagEditor) {
+    let area = helper::centered_rect(60, 50, frame.size());
+    let block = Block::default()
+        .title(editor.title.to_owned())
+        .borders(Borders::ALL);
+
+    let mut lines: Vec<Spans> = editor
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            let label = Span::styled(
+                format!("{: >13}: ", field.label()),
+                Style::default().fg(Color::Gray),
+            );
+            let value = match (editor.mixed[idx], idx == editor.selected) {
+                (true, _) => Span::styled(
+                    "(multiple values)",
+                    Style::default().add_modifier(Modifier::ITALIC),
+                ),
+                (false, true) => Span::styled(
+                    format!("{}_", editor.values[idx]),
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                (false, false) => Span::raw(editor.values[idx].to_owned()),
+            };
+            Spans::from(vec![label, value])
+        })
+        .collect();
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(match &editor.error {
+        Some(err) => Span::styled(err.to_owned(), Style::default().fg(Color::Red)),
+        None => Span::styled(
+            "Enter: save  Esc: cancel  separate multiple values with ;",
+            Style::default().fg(Color::Gray),
+        ),
+    }));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .alignment(Alignment::Left);
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
@@ -1,4 +1,5 @@
 pub mod requests {
+    use std::time::Duration;
 
     #[derive(Debug, Copy, Clone)]
     pub enum UIRequests {
@@ -12,6 +13,41 @@ pub mod requests {
         // ShowItemInfo,
         ShowSearch,
         SearchInput(char),
+        ShowImportReport,
+        NextTab,
+        // open the tag editor for the selected song or its whole album
+        EditTags,
+        EditAlbumTags,
+        EditInput(char),
+        Backspace,
+        // playlists
+        NewPlaylist,
+        NewSmartPlaylist,
+        EditSmartRules,
+        RenamePlaylist,
+        AddToPlaylist,
+        PlayPlaylist,
+        ShufflePlaylist,
+        ImportPlaylist,
+        ExportPlaylist,
+        // queue the selected song to play after the current one or after the other queued songs
+        PlayNext,
+        AddToQueue,
+        ClearQueue,
+        // switch to the next shuffle or repeat mode
+        CycleShuffle,
+        CycleRepeat,
+        // add similar songs when the queue runs out
+        ToggleRadio,
+        // show the stats of the next period
+        CycleStatsPeriod,
+        // rate the selected song from 1 to 5 stars, 0 removes the rating
+        SetRating(u8),
+        ToggleLoved,
+        // remove or move the selected item
+        Delete,
+        MoveUp,
+        MoveDown,
         //
         // UpdateBar,
         //
@@ -25,7 +61,14 @@ pub mod requests {
         Resume,
         Pause,
         PlayPause,
-        // Next,
+        // play the next song of the queue
+        Next,
+        // the song at this path played to the end
+        SongEnded(String),
+        // the song at this path couldn't be opened or decoded, skip it
+        SongFailed(String),
+        // load the current song paused at this position, after restoring the last session
+        Restore(Duration),
         // Previous,
         // Seek(u64),
         // ChangeVolume(f32),
@@ -36,6 +79,8 @@ pub mod requests {
     pub enum AppRequests {
         UIRequests(UIRequests),
         PlayerRequests(PlayerRequests),
+        // re-import the configured media directories
+        Rescan,
         Quit,
     }
 }